@@ -0,0 +1,162 @@
+use std::fmt::LowerHex;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, ReadBuf};
+
+use crate::convert::Modular;
+use crate::sha::{Digest, Hash};
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS> {
+    // hash the whole content of an async reader without blocking the runtime. Returns the number of bytes read
+    pub async fn hash_async_reader<R>(&mut self, mut reader: R) -> std::io::Result<u64>
+    where
+        R: AsyncRead + Unpin,
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        let mut buffer = vec![0u8; self.buffer_size];
+        let mut total_bytes_read = 0u64;
+
+        loop {
+            let bytes_read = reader.read(&mut buffer).await?;
+            if bytes_read == 0 {
+                break;
+            }
+            total_bytes_read += bytes_read as u64;
+            self.update(&buffer[..bytes_read]);
+        }
+
+        self.finalize();
+        Ok(total_bytes_read)
+    }
+}
+
+// async producers can write into the hash, e.g.: tokio::io::copy(&mut body, &mut sha256)
+// finalize() must be called once all data is written
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> AsyncWrite for Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+    T: Unpin,
+{
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        self.get_mut().update(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+// async reader adaptor: every byte read from the inner reader is also hashed
+pub struct AsyncHashingReader<R, H> {
+    inner: R,
+    hash: H,
+}
+
+impl<R: AsyncRead + Unpin, H: Digest + Unpin> AsyncHashingReader<R, H> {
+    pub fn new(inner: R, hash: H) -> Self {
+        AsyncHashingReader { inner, hash }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn hash(&self) -> &H {
+        &self.hash
+    }
+
+    // finalize the hash and give back the inner reader
+    pub fn finalize(mut self) -> (R, H) {
+        self.hash.finalize();
+        (self.inner, self.hash)
+    }
+}
+
+impl<R: AsyncRead + Unpin, H: Digest + Unpin> AsyncRead for AsyncHashingReader<R, H> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+
+        // only the bytes added by this read are hashed
+        let already_filled = buf.filled().len();
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = result {
+            this.hash.update(&buf.filled()[already_filled..]);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+    use tokio::io::AsyncWriteExt;
+
+    const SHA256_MILLION_A: &str =
+        "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0";
+
+    // writes 1 million 'a' in a duplex stream, by chunks smaller than the duplex buffer
+    fn million_a() -> tokio::io::DuplexStream {
+        let (mut client, server) = tokio::io::duplex(4096);
+        tokio::spawn(async move {
+            let chunk = [b'a'; 1000];
+            for _ in 0..1000 {
+                client.write_all(&chunk).await.unwrap();
+            }
+        });
+        server
+    }
+
+    #[tokio::test]
+    async fn async_reader() {
+        let mut sha256 = Sha256::new();
+        let bytes_read = sha256.hash_async_reader(million_a()).await;
+        assert_eq!(bytes_read.unwrap(), 1_000_000);
+        assert_eq!(sha256.to_string(), SHA256_MILLION_A);
+    }
+
+    #[tokio::test]
+    async fn async_write() {
+        let mut sha512 = Sha512::new();
+        let copied = tokio::io::copy(&mut million_a(), &mut sha512).await;
+        assert_eq!(copied.unwrap(), 1_000_000);
+        sha512.finalize();
+        assert_eq!(
+            sha512.to_string(),
+            "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
+        );
+    }
+
+    #[tokio::test]
+    async fn async_hashing_reader() {
+        let mut reader = AsyncHashingReader::new(million_a(), Sha256::new());
+        let mut copy = Vec::new();
+        assert!(reader.read_to_end(&mut copy).await.is_ok());
+
+        let (_, sha256) = reader.finalize();
+        assert_eq!(copy.len(), 1_000_000);
+        assert_eq!(sha256.to_string(), SHA256_MILLION_A);
+    }
+}
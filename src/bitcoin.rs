@@ -0,0 +1,186 @@
+use core::fmt::Display;
+
+use crate::sha::Digest;
+use crate::sha256::{const_block_hash, sha256_const, Sha256, IV};
+
+// SHA256d: SHA-256 of the SHA-256 digest, used by Bitcoin for block and transaction ids
+#[derive(Clone)]
+pub struct Sha256d {
+    sha256: Sha256,
+}
+
+impl Sha256d {
+    pub fn new() -> Self {
+        Sha256d {
+            sha256: Sha256::new(),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha256.update(data);
+    }
+
+    // the first digest is hashed again
+    pub fn finalize(&mut self) {
+        self.sha256.finalize();
+        let digest: [u8; 32] = self.sha256.digest();
+
+        self.sha256 = Sha256::new();
+        self.sha256.update(&digest);
+        self.sha256.finalize();
+    }
+
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.sha256.digest_into(out);
+    }
+
+    // digest in hash byte order, as used inside Bitcoin data structures
+    pub fn digest(&self) -> [u8; 32] {
+        self.sha256.digest()
+    }
+
+    // digest with its bytes reversed, as Bitcoin displays block and transaction ids
+    pub fn display_id(&self) -> [u8; 32] {
+        let mut id = self.digest();
+        id.reverse();
+        id
+    }
+}
+
+impl Default for Sha256d {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Digest for Sha256d {
+    fn update(&mut self, data: &[u8]) {
+        Sha256d::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        Sha256d::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        Sha256d::digest_into(self, out)
+    }
+}
+
+impl Display for Sha256d {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.sha256.fmt(f)
+    }
+}
+
+// BIP-340 tagged hash: SHA256(SHA256(tag) || SHA256(tag) || msg). The 64-byte prefix is exactly
+// one block, so its hash value (the midstate) is computed once and each tagged hash starts from it.
+// new() is const: e.g. const CHALLENGE: TaggedHash = TaggedHash::new(b"BIP0340/challenge");
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TaggedHash {
+    midstate: [u32; 8],
+}
+
+impl TaggedHash {
+    pub const fn new(tag: &[u8]) -> Self {
+        let tag_hash = sha256_const(tag);
+
+        let mut block = [0u8; 64];
+        let mut i = 0;
+        while i < 32 {
+            block[i] = tag_hash[i];
+            block[32 + i] = tag_hash[i];
+            i += 1;
+        }
+
+        TaggedHash {
+            midstate: const_block_hash(IV, &block),
+        }
+    }
+
+    // a SHA-256 hash having already processed the 64-byte prefix, to be given the message
+    pub fn hasher(&self) -> Sha256 {
+        let mut sha256 = Sha256::new();
+        sha256.hash = self.midstate;
+        sha256.length = 64;
+        sha256
+    }
+
+    pub fn hash(&self, message: &[u8]) -> [u8; 32] {
+        let mut sha256 = self.hasher();
+        sha256.update(message);
+        sha256.finalize();
+        sha256.digest()
+    }
+}
+
+// tags of BIP-340 (Schnorr signatures) and BIP-341 (Taproot)
+pub const BIP340_AUX: TaggedHash = TaggedHash::new(b"BIP0340/aux");
+pub const BIP340_NONCE: TaggedHash = TaggedHash::new(b"BIP0340/nonce");
+pub const BIP340_CHALLENGE: TaggedHash = TaggedHash::new(b"BIP0340/challenge");
+pub const TAP_LEAF: TaggedHash = TaggedHash::new(b"TapLeaf");
+pub const TAP_BRANCH: TaggedHash = TaggedHash::new(b"TapBranch");
+pub const TAP_TWEAK: TaggedHash = TaggedHash::new(b"TapTweak");
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn sha256d() {
+        let mut sha256d = Sha256d::new();
+        sha256d.update(b"hello");
+        sha256d.finalize();
+        assert_eq!(
+            sha256d.to_string(),
+            "9595c9df90075148eb06860365df33584b75bff782a510c6cd4883a419833d50"
+        );
+
+        // the genesis block id is the reversed digest of its header
+        let header = from_hex(
+            "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c",
+        );
+        let mut sha256d = Sha256d::new();
+        sha256d.update(&header);
+        sha256d.finalize();
+        assert_eq!(
+            hex(&sha256d.display_id()),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(
+            hex(&sha256d.digest()),
+            "6fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000"
+        );
+    }
+
+    #[test]
+    fn tagged_hash() {
+        assert_eq!(
+            hex(&BIP340_CHALLENGE.hash(b"abc")),
+            "770a5b7e7c304bbcc3ea107343ff951dd404312ef418db0c3b94e2ebfbb50087"
+        );
+        assert_eq!(
+            hex(&TAP_LEAF.hash(b"")),
+            "5212c288a377d1f8164962a5a13429f9ba6a7b84e59776a52c6637df2106facb"
+        );
+
+        // same as hashing the whole prefix
+        let tag_hash = sha256_const(b"BIP0340/challenge");
+        let mut sha256 = Sha256::new();
+        sha256.update(&tag_hash);
+        sha256.update(&tag_hash);
+        assert_eq!(sha256.hash, BIP340_CHALLENGE.hasher().hash);
+        assert_eq!(TaggedHash::new(b"BIP0340/challenge"), BIP340_CHALLENGE);
+    }
+}
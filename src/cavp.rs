@@ -0,0 +1,446 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Error, ErrorKind};
+use std::path::Path;
+
+use crate::sha::Digest;
+use crate::sha3::Sha3;
+
+// NIST CAVP response files (.rsp): ShortMsg/LongMsg with Len/Msg/MD, Monte Carlo with Seed/COUNT/MD,
+// SHAKE with Output/Outputlen (VariableOut without Len), and the Hash_DRBG/HMAC_DRBG files
+
+// a single test: only the fields found in the file are set
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TestCase {
+    pub count: Option<usize>,
+    pub length: Option<usize>, // message length in bits, not always a multiple of 8
+    pub message: Option<Vec<u8>>, // length / 8 bytes, rounded up
+    pub output_length: Option<usize>, // SHAKE output length in bits
+    pub md: Vec<u8>,           // MD or Output
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResponseFile {
+    pub comments: Vec<String>, // header lines, without the leading #
+    pub parameters: Vec<(String, String)>, // bracketed lines, e.g.: [L = 32]
+    pub seed: Option<Vec<u8>>, // Monte Carlo seed, or SHAKE Monte initial Msg
+    pub cases: Vec<TestCase>,
+}
+
+impl ResponseFile {
+    // value of a bracketed parameter, e.g.: parameter("L")
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // Monte Carlo files have a seed and only COUNT/MD cases
+    pub fn is_monte(&self) -> bool {
+        self.seed.is_some()
+    }
+}
+
+fn invalid_rsp(line: usize, msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid response file, line {}: {}", line, msg),
+    )
+}
+
+pub fn from_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+// split "key = value"
+fn key_value(text: &str) -> Option<(&str, &str)> {
+    let (key, value) = text.split_once('=')?;
+    Some((key.trim(), value.trim()))
+}
+
+pub fn parse<R: BufRead>(reader: R) -> std::io::Result<ResponseFile> {
+    let mut rsp = ResponseFile::default();
+    let mut case = TestCase::default();
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        let n = n + 1;
+
+        // intermediate values of the .txt files are indented
+        if line.starts_with(|c: char| c.is_whitespace()) {
+            continue;
+        }
+
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        if let Some(comment) = line.strip_prefix('#') {
+            rsp.comments.push(comment.trim().to_string());
+            continue;
+        }
+
+        if let Some(parameter) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            // some parameters have no value, e.g.: [Tested for Output of byte-oriented messages]
+            let (key, value) = key_value(parameter).unwrap_or((parameter, ""));
+            rsp.parameters.push((key.to_string(), value.to_string()));
+            continue;
+        }
+
+        let (key, value) = key_value(line).ok_or_else(|| invalid_rsp(n, "expected key = value"))?;
+        let number = || -> std::io::Result<usize> {
+            value.parse().map_err(|_| invalid_rsp(n, "not a number"))
+        };
+        let bytes = || from_hex(value).ok_or_else(|| invalid_rsp(n, "not an hex string"));
+
+        match key {
+            "Len" => case.length = Some(number()?),
+            "COUNT" => case.count = Some(number()?),
+            "Outputlen" => case.output_length = Some(number()?),
+            "Seed" => rsp.seed = Some(bytes()?),
+            // a message without any length is either of a SHAKE VariableOut test, after its output
+            // length, or the initial message of a SHAKE Monte Carlo test
+            "Msg" => match (case.length, case.output_length) {
+                (Some(length), _) => {
+                    let mut message = bytes()?;
+                    // the empty message is written as 00
+                    message.truncate(length.div_ceil(8));
+                    case.message = Some(message);
+                }
+                (None, Some(_)) => {
+                    let message = bytes()?;
+                    case.length = Some(8 * message.len());
+                    case.message = Some(message);
+                }
+                (None, None) => rsp.seed = Some(bytes()?),
+            },
+            // the digest ends the test case
+            "MD" | "Output" => {
+                case.md = bytes()?;
+                rsp.cases.push(std::mem::take(&mut case));
+            }
+            _ => return Err(invalid_rsp(n, &format!("unknown key {}", key))),
+        }
+    }
+
+    Ok(rsp)
+}
+
+pub fn read_rsp_file<P: AsRef<Path>>(path: P) -> std::io::Result<ResponseFile> {
+    parse(BufReader::new(File::open(path)?))
+}
+
+// DRBG test of the drbgvectors_pr_false or drbgvectors_pr_true files: instantiate, then either
+// reseed once or reseed before each generate with prediction resistance. The second of the two
+// generate calls gives ReturnedBits
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrbgTestCase {
+    pub count: Option<usize>,
+    pub entropy_input: Vec<u8>,
+    pub nonce: Vec<u8>,
+    pub personalization_string: Vec<u8>,
+    pub entropy_input_reseed: Option<Vec<u8>>,
+    pub additional_input_reseed: Option<Vec<u8>>,
+    pub additional_input: Vec<Vec<u8>>, // one per generate call
+    pub entropy_input_pr: Vec<Vec<u8>>, // one per generate call, with prediction resistance only
+    pub returned_bits: Vec<u8>,
+}
+
+// test cases sharing the same bracketed parameters, the first one being the hash function, e.g.:
+// [SHA-256] [PredictionResistance = True] [EntropyInputLen = 256] ...
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DrbgSection {
+    pub parameters: Vec<(String, String)>,
+    pub cases: Vec<DrbgTestCase>,
+}
+
+impl DrbgSection {
+    pub fn algorithm(&self) -> Option<&str> {
+        self.parameters.first().map(|(key, _)| key.as_str())
+    }
+
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn prediction_resistance(&self) -> bool {
+        self.parameter("PredictionResistance") == Some("True")
+    }
+}
+
+pub fn parse_drbg<R: BufRead>(reader: R) -> std::io::Result<Vec<DrbgSection>> {
+    let mut sections: Vec<DrbgSection> = Vec::new();
+    let mut case = DrbgTestCase::default();
+
+    for (n, line) in reader.lines().enumerate() {
+        let line = line?;
+        let n = n + 1;
+
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(parameter) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            // parameters following test cases start a new section
+            match sections.last_mut() {
+                Some(section) if section.cases.is_empty() => (),
+                _ => sections.push(DrbgSection::default()),
+            }
+
+            let (key, value) = key_value(parameter).unwrap_or((parameter, ""));
+            if let Some(section) = sections.last_mut() {
+                section
+                    .parameters
+                    .push((key.to_string(), value.to_string()));
+            }
+            continue;
+        }
+
+        let (key, value) = key_value(line).ok_or_else(|| invalid_rsp(n, "expected key = value"))?;
+        let bytes = || from_hex(value).ok_or_else(|| invalid_rsp(n, "not an hex string"));
+
+        match key {
+            "COUNT" => {
+                case.count = Some(value.parse().map_err(|_| invalid_rsp(n, "not a number"))?)
+            }
+            "EntropyInput" => case.entropy_input = bytes()?,
+            "Nonce" => case.nonce = bytes()?,
+            "PersonalizationString" => case.personalization_string = bytes()?,
+            "EntropyInputReseed" => case.entropy_input_reseed = Some(bytes()?),
+            "AdditionalInputReseed" => case.additional_input_reseed = Some(bytes()?),
+            "AdditionalInput" => case.additional_input.push(bytes()?),
+            "EntropyInputPR" => case.entropy_input_pr.push(bytes()?),
+            // the returned bits end the test case
+            "ReturnedBits" => {
+                case.returned_bits = bytes()?;
+                let section = sections
+                    .last_mut()
+                    .ok_or_else(|| invalid_rsp(n, "test case without parameters"))?;
+                section.cases.push(std::mem::take(&mut case));
+            }
+            _ => return Err(invalid_rsp(n, &format!("unknown key {}", key))),
+        }
+    }
+
+    Ok(sections)
+}
+
+pub fn read_drbg_file<P: AsRef<Path>>(path: P) -> std::io::Result<Vec<DrbgSection>> {
+    parse_drbg(BufReader::new(File::open(path)?))
+}
+
+// SHA-1 and SHA-2 Monte Carlo test: each message is the concatenation of the 3 previous digests,
+// the last of 1000 digests is a checkpoint and the seed of the next round
+pub fn sha2_monte<D, F>(new: F, seed: &[u8], checkpoints: usize) -> Vec<Vec<u8>>
+where
+    D: Digest,
+    F: Fn() -> D,
+{
+    let size = seed.len();
+    let mut seed = seed.to_vec();
+    let mut results = Vec::with_capacity(checkpoints);
+
+    for _ in 0..checkpoints {
+        // the 3 last digests
+        let mut md = [seed.clone(), seed.clone(), seed];
+        for _ in 3..1003 {
+            let mut digest = new();
+            for m in &md {
+                digest.update(m);
+            }
+            digest.finalize();
+
+            md.rotate_left(1);
+            digest.digest_into(&mut md[2][..size]);
+        }
+
+        let [_, _, last] = md;
+        results.push(last.clone());
+        seed = last;
+    }
+
+    results
+}
+
+// SHA-3 Monte Carlo test: each message is the previous digest
+pub fn sha3_monte<D, F>(new: F, seed: &[u8], checkpoints: usize) -> Vec<Vec<u8>>
+where
+    D: Digest,
+    F: Fn() -> D,
+{
+    let mut md = seed.to_vec();
+    let mut results = Vec::with_capacity(checkpoints);
+
+    for _ in 0..checkpoints {
+        for _ in 0..1000 {
+            let mut digest = new();
+            digest.update(&md);
+            digest.finalize();
+            digest.digest_into(&mut md);
+        }
+        results.push(md.clone());
+    }
+
+    results
+}
+
+// SHAKE Monte Carlo test: each message is the first 16 bytes of the previous output, and the
+// next output length in bytes comes from the last 2 bytes of the output
+pub fn shake_monte<F>(
+    new: F,
+    msg: &[u8],
+    min_length: usize,
+    max_length: usize,
+    checkpoints: usize,
+) -> Vec<Vec<u8>>
+where
+    F: Fn(usize) -> Sha3,
+{
+    let range = max_length - min_length + 1;
+    let mut output_length = max_length;
+    let mut output = msg.to_vec();
+    let mut results = Vec::with_capacity(checkpoints);
+
+    for _ in 0..checkpoints {
+        for _ in 0..1000 {
+            // zero-padded when the output is shorter than 16 bytes
+            let mut m = [0u8; 16];
+            let prefix = output.len().min(16);
+            m[..prefix].copy_from_slice(&output[..prefix]);
+
+            let mut shake = new(output_length);
+            shake.update(&m);
+            shake.finalize();
+            output = vec![0u8; output_length];
+            shake.digest_into(&mut output);
+
+            let rightmost =
+                u16::from_be_bytes([output[output_length - 2], output[output_length - 1]]);
+            output_length = min_length + rightmost as usize % range;
+        }
+        results.push(output.clone());
+    }
+
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::Sha256;
+    use std::io::Cursor;
+
+    const RSP: &str = "#  CAVS 11.0\r\n#  \"SHA-256 ShortMsg\" information\r\n\r\n[L = 32]\r\n\r\n\
+        Len = 0\r\nMsg = 00\r\nMD = e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855\r\n\r\n\
+        Len = 5\r\nMsg = 68\r\nMD = ff\r\n\r\n\
+        Len = 24\r\nMsg = 616263\r\nMD = ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad\r\n";
+
+    #[test]
+    fn parse_short_msg() {
+        let rsp = parse(Cursor::new(RSP)).unwrap();
+        assert_eq!(
+            rsp.comments,
+            vec!["CAVS 11.0", "\"SHA-256 ShortMsg\" information"]
+        );
+        assert_eq!(rsp.parameter("L"), Some("32"));
+        assert!(!rsp.is_monte());
+        assert_eq!(rsp.cases.len(), 3);
+
+        // empty message, bit-oriented message
+        assert_eq!(rsp.cases[0].message, Some(vec![]));
+        assert_eq!(rsp.cases[1].length, Some(5));
+        assert_eq!(rsp.cases[1].message, Some(vec![0x68]));
+
+        let mut sha256 = Sha256::new();
+        sha256.update(rsp.cases[2].message.as_ref().unwrap());
+        sha256.finalize();
+        assert_eq!(sha256.digest::<32>().to_vec(), rsp.cases[2].md);
+    }
+
+    #[test]
+    fn parse_monte() {
+        let rsp = parse(Cursor::new(
+            "[L = 32]\n\nSeed = 00ff\n\nCOUNT = 0\n\ti = 3\nMD = 01\n\nCOUNT = 1\nMD = 02\n",
+        ))
+        .unwrap();
+        assert!(rsp.is_monte());
+        assert_eq!(rsp.seed, Some(vec![0x00, 0xff]));
+        assert_eq!(rsp.cases[1].count, Some(1));
+        assert_eq!(rsp.cases[1].md, vec![0x02]);
+    }
+
+    #[test]
+    fn parse_variable_out() {
+        let rsp = parse(Cursor::new(
+            "[Input Length = 16]\n\nCOUNT = 0\nOutputlen = 24\nMsg = 6162\nOutput = 010203\n",
+        ))
+        .unwrap();
+        assert!(!rsp.is_monte());
+        assert_eq!(rsp.cases[0].length, Some(16));
+        assert_eq!(rsp.cases[0].message, Some(b"ab".to_vec()));
+        assert_eq!(rsp.cases[0].output_length, Some(24));
+    }
+
+    #[test]
+    fn invalid_files() {
+        assert!(parse(Cursor::new("Len = a\n")).is_err());
+        assert!(parse(Cursor::new("Len = 8\nMsg = 0g\n")).is_err());
+        assert!(parse(Cursor::new("Foo = 1\n")).is_err());
+        assert!(parse(Cursor::new("garbage\n")).is_err());
+    }
+
+    #[test]
+    fn parse_drbg_sections() {
+        let sections = parse_drbg(Cursor::new(
+            "# HMAC_DRBG\n[SHA-1]\n[PredictionResistance = True]\n\nCOUNT = 0\nEntropyInput = 01\n\
+             Nonce = 02\nPersonalizationString = \nAdditionalInput = \nEntropyInputPR = 03\n\
+             AdditionalInput = 04\nEntropyInputPR = 05\nReturnedBits = 06\n\n\
+             [SHA-256]\n[PredictionResistance = False]\n\nCOUNT = 0\nEntropyInput = 01\n\
+             Nonce = 02\nPersonalizationString = 07\nEntropyInputReseed = 08\n\
+             AdditionalInputReseed = \nAdditionalInput = \nAdditionalInput = \nReturnedBits = 09\n",
+        ))
+        .unwrap();
+
+        assert_eq!(sections.len(), 2);
+        assert_eq!(sections[0].algorithm(), Some("SHA-1"));
+        assert!(sections[0].prediction_resistance());
+        assert_eq!(sections[0].cases[0].additional_input, vec![vec![], vec![4]]);
+        assert_eq!(
+            sections[0].cases[0].entropy_input_pr,
+            vec![vec![3], vec![5]]
+        );
+
+        assert_eq!(sections[1].algorithm(), Some("SHA-256"));
+        assert!(!sections[1].prediction_resistance());
+        let case = &sections[1].cases[0];
+        assert_eq!(case.personalization_string, vec![7]);
+        assert_eq!(case.entropy_input_reseed, Some(vec![8]));
+        assert_eq!(case.additional_input_reseed, Some(vec![]));
+        assert_eq!(case.returned_bits, vec![9]);
+
+        assert!(parse_drbg(Cursor::new("COUNT = 0\nReturnedBits = 00\n")).is_err());
+        assert!(parse_drbg(Cursor::new("[SHA-1]\nFoo = 00\n")).is_err());
+    }
+
+    #[test]
+    fn hex() {
+        assert_eq!(from_hex("00ff1A"), Some(vec![0x00, 0xff, 0x1a]));
+        assert_eq!(from_hex("0"), None);
+        assert_eq!(to_hex(&[0x00, 0xff, 0x1a]), "00ff1a");
+    }
+}
@@ -1,7 +1,8 @@
-use std::convert::TryInto;
+use core::convert::TryInto;
 pub trait Modular<T> {
     fn add_modulo(&self, y: T) -> T;
     fn to_uint(buffer: &[u8]) -> T;
+    fn to_bytes(&self, buffer: &mut [u8]);
 }
 
 impl Modular<u32> for u32 {
@@ -16,6 +17,12 @@ impl Modular<u32> for u32 {
         #[cfg(target_endian = "big")]
         return u32::from_be_bytes(buffer.try_into().unwrap());
     }
+
+    // big endian representation, truncated to the buffer length
+    fn to_bytes(&self, buffer: &mut [u8]) {
+        let len = buffer.len();
+        buffer.copy_from_slice(&self.to_be_bytes()[..len]);
+    }
 }
 
 impl Modular<u64> for u64 {
@@ -30,4 +37,10 @@ impl Modular<u64> for u64 {
         #[cfg(target_endian = "big")]
         return u64::from_be_bytes(buffer.try_into().unwrap());
     }
+
+    // big endian representation, truncated to the buffer length
+    fn to_bytes(&self, buffer: &mut [u8]) {
+        let len = buffer.len();
+        buffer.copy_from_slice(&self.to_be_bytes()[..len]);
+    }
 }
@@ -0,0 +1,386 @@
+use std::fmt::LowerHex;
+use std::io::{Error, ErrorKind};
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+use crate::sha256::Sha256;
+use crate::sha512::Sha512;
+
+// SHA-crypt password hashes of /etc/shadow (Ulrich Drepper's "Unix crypt using SHA-256 and
+// SHA-512"): $5$ for SHA-256 and $6$ for SHA-512, e.g.:
+// $5$rounds=10000$saltstringsaltst$3xv.VbSHBb41AL9AvLeujZkZRBAwqFMz2.opqey6IcA
+
+pub const ROUNDS_DEFAULT: usize = 5000;
+pub const ROUNDS_MIN: usize = 1000;
+pub const ROUNDS_MAX: usize = 999_999_999;
+
+// longer salts are truncated, in bytes
+pub const SALT_MAX: usize = 16;
+
+const ROUNDS_PREFIX: &str = "rounds=";
+
+// the base64 alphabet of crypt, not the one of RFC 4648
+const ALPHABET: &[u8; 64] = b"./0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+// digest bytes encoded together in 4 characters, the last ones being encoded separately
+const SHA256_ORDER: [(usize, usize, usize); 10] = [
+    (0, 10, 20),
+    (21, 1, 11),
+    (12, 22, 2),
+    (3, 13, 23),
+    (24, 4, 14),
+    (15, 25, 5),
+    (6, 16, 26),
+    (27, 7, 17),
+    (18, 28, 8),
+    (9, 19, 29),
+];
+
+const SHA512_ORDER: [(usize, usize, usize); 21] = [
+    (0, 21, 42),
+    (22, 43, 1),
+    (44, 2, 23),
+    (3, 24, 45),
+    (25, 46, 4),
+    (47, 5, 26),
+    (6, 27, 48),
+    (28, 49, 7),
+    (50, 8, 29),
+    (9, 30, 51),
+    (31, 52, 10),
+    (53, 11, 32),
+    (12, 33, 54),
+    (34, 55, 13),
+    (56, 14, 35),
+    (15, 36, 57),
+    (37, 58, 16),
+    (59, 17, 38),
+    (18, 39, 60),
+    (40, 61, 19),
+    (62, 20, 41),
+];
+
+// n characters for the 24-bit value b2 b1 b0, least significant 6 bits first
+fn encode_24bit(out: &mut String, b2: u8, b1: u8, b0: u8, n: usize) {
+    let mut w = (b2 as u32) << 16 | (b1 as u32) << 8 | b0 as u32;
+    for _ in 0..n {
+        out.push(ALPHABET[(w & 0x3f) as usize] as char);
+        w >>= 6;
+    }
+}
+
+// salt made of random bytes, e.g. read from /dev/urandom
+pub fn encode_salt(random: &[u8]) -> String {
+    random
+        .iter()
+        .take(SALT_MAX)
+        .map(|b| ALPHABET[(b & 0x3f) as usize] as char)
+        .collect()
+}
+
+// the 64 bytes buffer holds the digest_size bytes of the final digest
+fn sha_crypt<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+    password: &[u8],
+    salt: &[u8],
+    rounds: usize,
+) -> [u8; 64]
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    let n = hash.digest_size;
+    let digest = |h: &mut Hash<T, BLOCKSIZE, ROUNDS>| {
+        h.finalize();
+        let mut d = [0u8; 64];
+        h.digest_into(&mut d[..n]);
+        d
+    };
+
+    // B = H(password || salt || password)
+    let mut h = hash.fork();
+    h.update(password);
+    h.update(salt);
+    h.update(password);
+    let b = digest(&mut h);
+
+    // A = H(password || salt || B repeated for the password length || B or password for each bit
+    // of the password length)
+    let mut h = hash.fork();
+    h.update(password);
+    h.update(salt);
+    let mut length = password.len();
+    while length > n {
+        h.update(&b[..n]);
+        length -= n;
+    }
+    h.update(&b[..length]);
+
+    length = password.len();
+    while length > 0 {
+        if length & 1 == 1 {
+            h.update(&b[..n]);
+        } else {
+            h.update(password);
+        }
+        length >>= 1;
+    }
+    let a = digest(&mut h);
+
+    // P: H(password repeated password length times) repeated to the password length
+    let mut h = hash.fork();
+    for _ in 0..password.len() {
+        h.update(password);
+    }
+    let dp = digest(&mut h);
+    let p: Vec<u8> = dp[..n]
+        .iter()
+        .cycle()
+        .take(password.len())
+        .copied()
+        .collect();
+
+    // S: H(salt repeated 16 + A[0] times) repeated to the salt length
+    let mut h = hash.fork();
+    for _ in 0..16 + a[0] as usize {
+        h.update(salt);
+    }
+    let ds = digest(&mut h);
+    let s: Vec<u8> = ds[..n].iter().cycle().take(salt.len()).copied().collect();
+
+    // the rounds alternate C and P, adding S or P depending on the round number
+    let mut c = a;
+    for i in 0..rounds {
+        let mut h = hash.fork();
+        if i & 1 == 1 {
+            h.update(&p);
+        } else {
+            h.update(&c[..n]);
+        }
+        if i % 3 != 0 {
+            h.update(&s);
+        }
+        if i % 7 != 0 {
+            h.update(&p);
+        }
+        if i & 1 == 1 {
+            h.update(&c[..n]);
+        } else {
+            h.update(&p);
+        }
+        c = digest(&mut h);
+    }
+
+    c
+}
+
+// $5$[rounds=N$]salt$hash, rounds being written only if given
+fn crypt_string(id: u8, rounds: Option<usize>, salt: &str, encoded: &str) -> String {
+    let mut crypt = format!("${}$", id as char);
+    if let Some(rounds) = rounds {
+        crypt.push_str(&format!("{}{}$", ROUNDS_PREFIX, rounds));
+    }
+    crypt.push_str(salt);
+    crypt.push('$');
+    crypt.push_str(encoded);
+    crypt
+}
+
+// at most SALT_MAX bytes, without splitting a character so that the crypt string stays valid
+fn truncate_salt(salt: &str) -> &str {
+    let mut end = salt.len().min(SALT_MAX);
+    while !salt.is_char_boundary(end) {
+        end -= 1;
+    }
+    &salt[..end]
+}
+
+fn clamp_rounds(rounds: Option<usize>) -> usize {
+    rounds
+        .unwrap_or(ROUNDS_DEFAULT)
+        .clamp(ROUNDS_MIN, ROUNDS_MAX)
+}
+
+pub fn sha256_crypt(password: &[u8], salt: &str, rounds: Option<usize>) -> String {
+    let salt = truncate_salt(salt);
+    let rounds = rounds.map(|r| clamp_rounds(Some(r)));
+    let c = sha_crypt(
+        &Sha256::new(),
+        password,
+        salt.as_bytes(),
+        clamp_rounds(rounds),
+    );
+
+    let mut encoded = String::with_capacity(43);
+    for (b2, b1, b0) in SHA256_ORDER {
+        encode_24bit(&mut encoded, c[b2], c[b1], c[b0], 4);
+    }
+    encode_24bit(&mut encoded, 0, c[31], c[30], 3);
+
+    crypt_string(b'5', rounds, salt, &encoded)
+}
+
+pub fn sha512_crypt(password: &[u8], salt: &str, rounds: Option<usize>) -> String {
+    let salt = truncate_salt(salt);
+    let rounds = rounds.map(|r| clamp_rounds(Some(r)));
+    let c = sha_crypt(
+        &Sha512::new(),
+        password,
+        salt.as_bytes(),
+        clamp_rounds(rounds),
+    );
+
+    let mut encoded = String::with_capacity(86);
+    for (b2, b1, b0) in SHA512_ORDER {
+        encode_24bit(&mut encoded, c[b2], c[b1], c[b0], 4);
+    }
+    encode_24bit(&mut encoded, 0, 0, c[63], 2);
+
+    crypt_string(b'6', rounds, salt, &encoded)
+}
+
+// setting is $5$ or $6$, an optional rounds=N$ and the salt, up to the next $ if any: a complete
+// crypt string can be given, only its salt and rounds are used
+pub fn crypt(password: &[u8], setting: &str) -> std::io::Result<String> {
+    let (id, rest) = match setting.get(..3) {
+        Some("$5$") => (b'5', &setting[3..]),
+        Some("$6$") => (b'6', &setting[3..]),
+        _ => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "crypt setting should start with $5$ or $6$",
+            ))
+        }
+    };
+
+    // rounds=N is only a parameter when N is a number followed by $, otherwise it is the salt
+    let mut rounds = None;
+    let mut salt = rest;
+    if let Some((number, after)) = rest
+        .strip_prefix(ROUNDS_PREFIX)
+        .and_then(|r| r.split_once('$'))
+    {
+        if let Ok(number) = number.parse::<usize>() {
+            rounds = Some(number);
+            salt = after;
+        }
+    }
+
+    let salt = salt.split('$').next().unwrap_or_default();
+    Ok(match id {
+        b'5' => sha256_crypt(password, salt, rounds),
+        _ => sha512_crypt(password, salt, rounds),
+    })
+}
+
+// the password gives the same crypt string, compared without stopping at the first difference
+pub fn verify(password: &[u8], crypt_string: &str) -> bool {
+    match crypt(password, crypt_string) {
+        Ok(computed) => {
+            computed.len() == crypt_string.len()
+                && computed
+                    .bytes()
+                    .zip(crypt_string.bytes())
+                    .fold(0, |diff, (a, b)| diff | (a ^ b))
+                    == 0
+        }
+        Err(_) => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // test vectors of the specification
+    const VECTORS: [(&str, &str, &str, &str); 7] = [
+        (
+            "$5$saltstring",
+            "Hello world!",
+            "$5$saltstring$5B8vYYiY.CVt1RlTTf8KbXBH3hsxY/GNooZaBBGWEc5",
+            "$6$saltstring$svn8UoSVapNtMuq1ukKS4tPQd8iKwSMHWjl/O817G3uBnIFNjnQJuesI68u4OTLiBFdcbYEdFCoEOfaS35inz1",
+        ),
+        (
+            "$5$rounds=10000$saltstringsaltstring",
+            "Hello world!",
+            "$5$rounds=10000$saltstringsaltst$3xv.VbSHBb41AL9AvLeujZkZRBAwqFMz2.opqey6IcA",
+            "$6$rounds=10000$saltstringsaltst$OW1/O6BYHV6BcXZu8QVeXbDWra3Oeqh0sbHbbMCVNSnCM/UrjmM0Dp8vOuZeHBy/YTBmSK6H9qs/y3RnOaw5v.",
+        ),
+        (
+            "$5$rounds=5000$toolongsaltstring",
+            "This is just a test",
+            "$5$rounds=5000$toolongsaltstrin$Un/5jzAHMgOGZ5.mWJpuVolil07guHPvOW8mGRcvxa5",
+            "$6$rounds=5000$toolongsaltstrin$lQ8jolhgVRVhY4b5pZKaysCLi0QBxGoNeKQzQ3glMhwllF7oGDZxUhx1yxdYcz/e1JSbq3y6JMxxl8audkUEm0",
+        ),
+        (
+            "$5$rounds=1400$anotherlongsaltstring",
+            "a very much longer text to encrypt.  This one even stretches over morethan one line.",
+            "$5$rounds=1400$anotherlongsalts$Rx.j8H.h8HjEDGomFU8bDkXm3XIUnzyxf12oP84Bnq1",
+            "$6$rounds=1400$anotherlongsalts$POfYwTEok97VWcjxIiSOjiykti.o/pQs.wPvMxQ6Fm7I6IoYN3CmLs66x9t0oSwbtEW7o7UmJEiDwGqd8p4ur1",
+        ),
+        (
+            "$5$rounds=77777$short",
+            "we have a short salt string but not a short password",
+            "$5$rounds=77777$short$JiO1O3ZpDAxGJeaDIuqCoEFysAe1mZNJRs3pw0KQRd/",
+            "$6$rounds=77777$short$WuQyW2YR.hBNpjjRhpYD/ifIw05xdfeEyQoMxIXbkvr0gge1a1x3yRULJ5CCaUeOxFmtlcGZelFl5CxtgfiAc0",
+        ),
+        (
+            "$5$rounds=123456$asaltof16chars..",
+            "a short string",
+            "$5$rounds=123456$asaltof16chars..$gP3VQ/6X7UUEW3HkBn2w1/Ptq2jxPyzV/cZKmF/wJvD",
+            "$6$rounds=123456$asaltof16chars..$BtCwjqMJGx5hrJhZywWvt0RLE8uZ4oPwcelCjmw2kSYu.Ec6ycULevoBK25fs2xXgMNrCzIMVcgEJAstJeonj1",
+        ),
+        (
+            "$5$rounds=10$roundstoolow",
+            "the minimum number is still observed",
+            "$5$rounds=1000$roundstoolow$yfvwcWrQ8l/K0DAWyuPMDNHpIVlTQebY9l/gL972bIC",
+            "$6$rounds=1000$roundstoolow$kUMsbe306n21p9R.FRkW3IGn.S9NPN0x50YhH1xhLsPuWGsUSklZt58jaTfF4ZEQpyUNGc0dqbpBYYBaHHrsX.",
+        ),
+    ];
+
+    #[test]
+    fn specification() {
+        for (setting, password, sha256, sha512) in &VECTORS {
+            assert_eq!(crypt(password.as_bytes(), setting).unwrap(), *sha256);
+            let setting = format!("$6${}", &setting[3..]);
+            assert_eq!(crypt(password.as_bytes(), &setting).unwrap(), *sha512);
+        }
+    }
+
+    #[test]
+    fn verify_password() {
+        let (_, password, sha256, sha512) = VECTORS[0];
+        assert!(verify(password.as_bytes(), sha256));
+        assert!(verify(password.as_bytes(), sha512));
+        assert!(!verify(b"Hello world", sha256));
+        assert!(!verify(password.as_bytes(), &sha256[..sha256.len() - 1]));
+        assert!(!verify(password.as_bytes(), "$1$saltstring$"));
+    }
+
+    #[test]
+    fn settings() {
+        // empty salt, no rounds=, rounds= as part of the salt
+        assert_eq!(
+            crypt(b"pw", "$5$$").unwrap(),
+            "$5$$EPxZX4DoQWu4KoghxUArtr9dmHmQzOXFqq.aJMdG0bA"
+        );
+        assert_eq!(
+            crypt(b"pw", "$5$rounds=5000$salt").unwrap(),
+            "$5$rounds=5000$salt$Oo0nc86Ktkc05wTAggFOZIQJhfxhAZY1mlIogZJN.i."
+        );
+        assert!(crypt(b"pw", "$5$rounds=x$salt")
+            .unwrap()
+            .starts_with("$5$rounds=x$"));
+        assert!(crypt(b"pw", "$2b$salt").is_err());
+
+        // a salt truncated to 16 bytes keeps whole characters
+        let crypt_string = crypt(b"pw", "$5$ééééééééé").unwrap();
+        assert!(crypt_string.starts_with("$5$éééééééé$"));
+        assert!(verify(b"pw", &crypt_string));
+
+        assert_eq!(encode_salt(&[0, 1, 63, 64, 255]), "./z.z");
+        assert_eq!(encode_salt(&[0; 20]).len(), SALT_MAX);
+    }
+}
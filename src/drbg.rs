@@ -0,0 +1,552 @@
+use core::fmt::{Display, LowerHex};
+
+use crate::convert::Modular;
+use crate::hmac::Hmac;
+use crate::sha::Hash;
+
+// deterministic random bit generators of NIST SP 800-90A, over any Hash variant. The entropy input
+// and nonce are given by the caller: e.g. RFC 6979 nonces use the private key and the message hash
+
+// at most 2^19 bits per generate call
+pub const MAX_BYTES_PER_REQUEST: usize = 1 << 16;
+
+// reseed required after 2^48 generate calls
+pub const RESEED_INTERVAL: u64 = 1 << 48;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DrbgError {
+    ReseedRequired,
+    RequestTooLarge,
+    EntropyTooShort,
+}
+
+impl Display for DrbgError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            DrbgError::ReseedRequired => write!(f, "reseed required"),
+            DrbgError::RequestTooLarge => {
+                write!(f, "at most {} bytes per request", MAX_BYTES_PER_REQUEST)
+            }
+            DrbgError::EntropyTooShort => {
+                write!(f, "entropy input shorter than the security strength")
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DrbgError {}
+
+pub trait Drbg {
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) -> Result<(), DrbgError>;
+    fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError>;
+
+    // the additional input is used by the reseed, fresh entropy being given for each request
+    fn generate_with_prediction_resistance(
+        &mut self,
+        out: &mut [u8],
+        entropy_input: &[u8],
+        additional_input: &[u8],
+    ) -> Result<(), DrbgError> {
+        self.reseed(entropy_input, additional_input)?;
+        self.generate(out, &[])
+    }
+}
+
+// in bytes: 128 bits for SHA-1, 192 bits for SHA-224, 256 bits for the longer SHA-2 digests
+fn security_strength(digest_size: usize) -> usize {
+    match digest_size {
+        0..=20 => 16,
+        21..=28 => 24,
+        _ => 32,
+    }
+}
+
+fn check_entropy(entropy_input: &[u8], digest_size: usize) -> Result<(), DrbgError> {
+    if entropy_input.len() < security_strength(digest_size) {
+        return Err(DrbgError::EntropyTooShort);
+    }
+    Ok(())
+}
+
+fn check_request(out: &[u8], reseed_counter: u64, reseed_interval: u64) -> Result<(), DrbgError> {
+    if out.len() > MAX_BYTES_PER_REQUEST {
+        return Err(DrbgError::RequestTooLarge);
+    }
+    if reseed_counter > reseed_interval {
+        return Err(DrbgError::ReseedRequired);
+    }
+    Ok(())
+}
+
+// HMAC_DRBG (SP 800-90A 10.1.2): K and V are digest_size bytes
+#[derive(Clone)]
+pub struct HmacDrbg<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
+    hash: Hash<T, BLOCKSIZE, ROUNDS>,
+    key: [u8; 64],
+    value: [u8; 64],
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> HmacDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // e.g.: HmacDrbg::new(Sha256::new(), &entropy, &nonce, b"personalization string")
+    pub fn new(
+        hash: Hash<T, BLOCKSIZE, ROUNDS>,
+        entropy_input: &[u8],
+        nonce: &[u8],
+        personalization_string: &[u8],
+    ) -> Result<Self, DrbgError> {
+        check_entropy(entropy_input, hash.digest_size)?;
+
+        let mut drbg = HmacDrbg {
+            hash,
+            key: [0x00; 64],
+            value: [0x01; 64],
+            reseed_counter: 1,
+            reseed_interval: RESEED_INTERVAL,
+        };
+        drbg.update(&[entropy_input, nonce, personalization_string]);
+        Ok(drbg)
+    }
+
+    // generate calls allowed before a reseed
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+
+    // HMAC(K, V || parts)
+    fn hmac(&self, parts: &[&[u8]]) -> [u8; 64] {
+        let size = self.hash.digest_size;
+        let mut hmac = Hmac::new(self.hash.fork(), &self.key[..size]);
+        hmac.update(&self.value[..size]);
+        for part in parts {
+            hmac.update(part);
+        }
+        hmac.finalize();
+
+        let mut mac = [0u8; 64];
+        hmac.digest_into(&mut mac[..size]);
+        mac
+    }
+
+    // HMAC_DRBG_Update, the provided data being the concatenation of parts
+    fn update(&mut self, provided_data: &[&[u8]]) {
+        for prefix in [[0x00u8], [0x01]] {
+            let mut parts = [&[][..]; 4];
+            parts[0] = &prefix;
+            parts[1..=provided_data.len()].copy_from_slice(provided_data);
+
+            self.key = self.hmac(&parts[..=provided_data.len()]);
+            self.value = self.hmac(&[]);
+
+            if provided_data.iter().all(|data| data.is_empty()) {
+                break;
+            }
+        }
+    }
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Drbg for HmacDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_entropy(entropy_input, self.hash.digest_size)?;
+
+        self.update(&[entropy_input, additional_input]);
+        self.reseed_counter = 1;
+        Ok(())
+    }
+
+    fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_request(out, self.reseed_counter, self.reseed_interval)?;
+
+        if !additional_input.is_empty() {
+            self.update(&[additional_input]);
+        }
+
+        for chunk in out.chunks_mut(self.hash.digest_size) {
+            self.value = self.hmac(&[]);
+            chunk.copy_from_slice(&self.value[..chunk.len()]);
+        }
+
+        self.update(&[additional_input]);
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+// Hash_DRBG (SP 800-90A 10.1.1): V and C are seedlen bytes, 440 bits for the 64-byte block hashes
+// and 888 bits for the 128-byte block hashes
+#[derive(Clone)]
+pub struct HashDrbg<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
+    hash: Hash<T, BLOCKSIZE, ROUNDS>,
+    value: [u8; 111],
+    constant: [u8; 111],
+    seed_len: usize,
+    reseed_counter: u64,
+    reseed_interval: u64,
+}
+
+// x = (x + y) mod 2^(8 * x.len()), big endian
+fn add_into(x: &mut [u8], y: &[u8]) {
+    let mut carry = 0u16;
+    let mut y = y.iter().rev();
+
+    for byte in x.iter_mut().rev() {
+        let sum = *byte as u16 + *y.next().unwrap_or(&0) as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> HashDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // e.g.: HashDrbg::new(Sha512::new(), &entropy, &nonce, b"personalization string")
+    pub fn new(
+        hash: Hash<T, BLOCKSIZE, ROUNDS>,
+        entropy_input: &[u8],
+        nonce: &[u8],
+        personalization_string: &[u8],
+    ) -> Result<Self, DrbgError> {
+        check_entropy(entropy_input, hash.digest_size)?;
+
+        let mut drbg = HashDrbg {
+            hash,
+            value: [0u8; 111],
+            constant: [0u8; 111],
+            seed_len: if BLOCKSIZE == 64 { 55 } else { 111 },
+            reseed_counter: 1,
+            reseed_interval: RESEED_INTERVAL,
+        };
+        drbg.new_seed(&[entropy_input, nonce, personalization_string]);
+        Ok(drbg)
+    }
+
+    // generate calls allowed before a reseed
+    pub fn with_reseed_interval(mut self, reseed_interval: u64) -> Self {
+        self.reseed_interval = reseed_interval;
+        self
+    }
+
+    pub fn reseed_counter(&self) -> u64 {
+        self.reseed_counter
+    }
+
+    // Hash(parts), digest_size bytes
+    fn digest(&self, parts: &[&[u8]]) -> [u8; 64] {
+        let mut hash = self.hash.fork();
+        for part in parts {
+            hash.update(part);
+        }
+        hash.finalize();
+
+        let mut digest = [0u8; 64];
+        hash.digest_into(&mut digest[..self.hash.digest_size]);
+        digest
+    }
+
+    // Hash_df: out.len() bytes derived from the concatenation of parts
+    fn hash_df(&self, parts: &[&[u8]], out: &mut [u8]) {
+        let bits = (8 * out.len() as u32).to_be_bytes();
+
+        for (counter, chunk) in out.chunks_mut(self.hash.digest_size).enumerate() {
+            let mut hash = self.hash.fork();
+            hash.update(&[counter as u8 + 1]);
+            hash.update(&bits);
+            for part in parts {
+                hash.update(part);
+            }
+            hash.finalize();
+
+            let mut digest = [0u8; 64];
+            hash.digest_into(&mut digest[..self.hash.digest_size]);
+            chunk.copy_from_slice(&digest[..chunk.len()]);
+        }
+    }
+
+    // V = Hash_df(seed material), C = Hash_df(0x00 || V)
+    fn new_seed(&mut self, seed_material: &[&[u8]]) {
+        let n = self.seed_len;
+
+        let mut value = [0u8; 111];
+        self.hash_df(seed_material, &mut value[..n]);
+        self.value = value;
+
+        let mut constant = [0u8; 111];
+        self.hash_df(&[&[0x00], &self.value[..n]], &mut constant[..n]);
+        self.constant = constant;
+
+        self.reseed_counter = 1;
+    }
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Drbg for HashDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn reseed(&mut self, entropy_input: &[u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_entropy(entropy_input, self.hash.digest_size)?;
+
+        let value = self.value;
+        self.new_seed(&[
+            &[0x01],
+            &value[..self.seed_len],
+            entropy_input,
+            additional_input,
+        ]);
+        Ok(())
+    }
+
+    fn generate(&mut self, out: &mut [u8], additional_input: &[u8]) -> Result<(), DrbgError> {
+        check_request(out, self.reseed_counter, self.reseed_interval)?;
+
+        let n = self.seed_len;
+        let size = self.hash.digest_size;
+
+        if !additional_input.is_empty() {
+            let w = self.digest(&[&[0x02], &self.value[..n], additional_input]);
+            add_into(&mut self.value[..n], &w[..size]);
+        }
+
+        // Hashgen
+        let mut data = self.value;
+        for chunk in out.chunks_mut(size) {
+            let w = self.digest(&[&data[..n]]);
+            chunk.copy_from_slice(&w[..chunk.len()]);
+            add_into(&mut data[..n], &[1]);
+        }
+
+        // V = V + H + C + reseed_counter
+        let h = self.digest(&[&[0x03], &self.value[..n]]);
+        let constant = self.constant;
+        add_into(&mut self.value[..n], &h[..size]);
+        add_into(&mut self.value[..n], &constant[..n]);
+        add_into(&mut self.value[..n], &self.reseed_counter.to_be_bytes());
+
+        self.reseed_counter += 1;
+        Ok(())
+    }
+}
+
+// rand_core::RngCore: fill_bytes panics when a reseed is required
+#[cfg(feature = "rng")]
+fn try_fill<D: Drbg>(drbg: &mut D, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+    for chunk in dest.chunks_mut(MAX_BYTES_PER_REQUEST) {
+        drbg.generate(chunk, &[]).map_err(|e| {
+            let code = rand_core::Error::CUSTOM_START + e as u32;
+            rand_core::Error::from(core::num::NonZeroU32::new(code).unwrap())
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(feature = "rng")]
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> rand_core::RngCore
+    for HmacDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .unwrap_or_else(|e| panic!("HMAC_DRBG: {}", e))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        try_fill(self, dest)
+    }
+}
+
+#[cfg(feature = "rng")]
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> rand_core::CryptoRng
+    for HmacDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+}
+
+#[cfg(feature = "rng")]
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> rand_core::RngCore
+    for HashDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn next_u32(&mut self) -> u32 {
+        rand_core::impls::next_u32_via_fill(self)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        rand_core::impls::next_u64_via_fill(self)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        self.try_fill_bytes(dest)
+            .unwrap_or_else(|e| panic!("Hash_DRBG: {}", e))
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        try_fill(self, dest)
+    }
+}
+
+#[cfg(feature = "rng")]
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> rand_core::CryptoRng
+    for HashDrbg<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    // RFC 6979 A.2.5: deterministic ECDSA nonce of P-256 with SHA-256, message "sample". The hash
+    // of the message is smaller than the curve order, and so is the first candidate
+    #[test]
+    fn rfc6979_nonce() {
+        let private_key =
+            from_hex("c9afa9d845ba75166b5c215767b1d6934e50c3db36e89b127b8a622b120f6721");
+        let mut sha256 = Sha256::new();
+        sha256.update(b"sample");
+        sha256.finalize();
+        let h1: [u8; 32] = sha256.digest();
+
+        let mut drbg = HmacDrbg::new(Sha256::new(), &private_key, &h1, &[]).unwrap();
+        let mut k = [0u8; 32];
+        drbg.generate(&mut k, &[]).unwrap();
+        assert_eq!(
+            k.to_vec(),
+            from_hex("a6e3c57dd01abe90086538398355dd4c3b17aa873382b0f24d6129493d8aad60")
+        );
+    }
+
+    #[test]
+    fn reseed_counter() {
+        let entropy = [0x42u8; 32];
+        let mut out = [0u8; 16];
+
+        let mut drbg = HashDrbg::new(Sha512::new(), &entropy, &[1, 2], &[])
+            .unwrap()
+            .with_reseed_interval(2);
+        drbg.generate(&mut out, &[]).unwrap();
+        drbg.generate(&mut out, b"additional input").unwrap();
+        assert_eq!(drbg.reseed_counter(), 3);
+        assert_eq!(drbg.generate(&mut out, &[]), Err(DrbgError::ReseedRequired));
+
+        drbg.reseed(&entropy, &[]).unwrap();
+        assert_eq!(drbg.reseed_counter(), 1);
+        assert!(drbg.generate(&mut out, &[]).is_ok());
+
+        // prediction resistance reseeds before each request
+        let mut drbg = HmacDrbg::new(Sha1::new(), &entropy, &[], &[])
+            .unwrap()
+            .with_reseed_interval(1);
+        for _ in 0..3 {
+            drbg.generate_with_prediction_resistance(&mut out, &entropy, &[])
+                .unwrap();
+        }
+        assert_eq!(drbg.generate(&mut out, &[]), Err(DrbgError::ReseedRequired));
+    }
+
+    #[test]
+    fn invalid_requests() {
+        assert_eq!(
+            HmacDrbg::new(Sha256::new(), &[0u8; 16], &[], &[]).err(),
+            Some(DrbgError::EntropyTooShort)
+        );
+        assert!(HashDrbg::new(Sha1::new(), &[0u8; 16], &[], &[]).is_ok());
+
+        let mut drbg = HashDrbg::new(Sha256::new(), &[0u8; 32], &[], &[]).unwrap();
+        let mut out = vec![0u8; MAX_BYTES_PER_REQUEST + 1];
+        assert_eq!(
+            drbg.generate(&mut out, &[]),
+            Err(DrbgError::RequestTooLarge)
+        );
+        assert_eq!(
+            drbg.reseed(&[0u8; 31], &[]),
+            Err(DrbgError::EntropyTooShort)
+        );
+    }
+
+    #[test]
+    fn add_modulo() {
+        let mut x = [0x00, 0xff, 0xff];
+        add_into(&mut x, &[0x01]);
+        assert_eq!(x, [0x01, 0x00, 0x00]);
+
+        // the carry out of the leftmost byte is dropped
+        let mut x = [0xff, 0xff];
+        add_into(&mut x, &[0x00, 0x00, 0x02]);
+        assert_eq!(x, [0x00, 0x01]);
+    }
+
+    #[cfg(feature = "rng")]
+    #[test]
+    fn rng_core() {
+        use rand_core::RngCore;
+
+        let mut drbg = HmacDrbg::new(Sha256::new(), &[7u8; 32], &[], &[]).unwrap();
+        let mut expected = drbg.clone();
+
+        let mut bytes = [0u8; 8];
+        expected.generate(&mut bytes, &[]).unwrap();
+        assert_eq!(drbg.next_u64(), u64::from_le_bytes(bytes));
+
+        let mut drbg = drbg.with_reseed_interval(1);
+        assert!(drbg.try_fill_bytes(&mut bytes).is_err());
+    }
+}
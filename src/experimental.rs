@@ -0,0 +1,260 @@
+use core::fmt::LowerHex;
+use core::ops::{BitAnd, BitXor, Shr};
+
+use crate::convert::Modular;
+use crate::scramble::{Scramble, Shifter};
+use crate::sha::Hash;
+
+// non-standard SHA-2 variants for cryptanalysis research: reduced number of rounds, custom IV,
+// K constants or rotation amounts. The hash built is not SHA-2 anymore and its standard field is
+// false, e.g.: let sha = Sha2Builder::new(Sha256::new()).with_rounds(24).build();
+pub struct Sha2Builder<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
+    hash: Hash<T, BLOCKSIZE, ROUNDS>,
+}
+
+#[allow(non_snake_case)]
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Sha2Builder<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // parameters not set are those of the fresh SHA-2 hash given, e.g. Sha256::new()
+    pub fn new(hash: Hash<T, BLOCKSIZE, ROUNDS>) -> Self {
+        // SHA-1 is also a Hash but with 80 rounds of 32-bit words
+        assert!(
+            (BLOCKSIZE, ROUNDS) == (64, 64) || (BLOCKSIZE, ROUNDS) == (128, 80),
+            "not a SHA-2 hash"
+        );
+        // the new parameters would apply to a state computed with the old ones
+        assert!(
+            hash.length == 0 && hash.block_len == 0,
+            "the hash has already been updated"
+        );
+        Sha2Builder { hash }
+    }
+
+    // only the first rounds are computed, with the first K constants
+    pub fn with_rounds(mut self, rounds: usize) -> Self {
+        assert!(rounds <= ROUNDS, "at most {} rounds", ROUNDS);
+        self.hash.rounds = rounds;
+        self
+    }
+
+    // initial hash value H(0)
+    pub fn with_iv(mut self, iv: [T; 8]) -> Self {
+        self.hash.hash = iv;
+        self
+    }
+
+    pub fn with_k_constants(mut self, k_constants: [T; ROUNDS]) -> Self {
+        self.hash.k_constants = k_constants;
+        self
+    }
+
+    // truncated digest, in bytes
+    pub fn with_digest_size(mut self, digest_size: usize) -> Self {
+        assert!(
+            digest_size <= 8 * core::mem::size_of::<T>(),
+            "digest size is at most 8 words"
+        );
+        self.hash.digest_size = digest_size;
+        self
+    }
+
+    // σ0(X) = RotR(X,A) ⊕ RotR(X,B) ⊕ X >> C, used by the message schedule
+    pub fn with_σ0<const A: u8, const B: u8, const C: u8>(mut self) -> Self
+    where
+        T: Shr<Output = T>,
+        T: BitXor<Output = T>,
+        T: Shifter<T>,
+    {
+        Self::check_amounts(&[A, B, C]);
+        self.hash.scramble_funcs.σ0 = Scramble::<T>::σ::<A, B, C>;
+        self
+    }
+
+    // σ1(X) = RotR(X,A) ⊕ RotR(X,B) ⊕ X >> C, used by the message schedule
+    pub fn with_σ1<const A: u8, const B: u8, const C: u8>(mut self) -> Self
+    where
+        T: Shr<Output = T>,
+        T: BitXor<Output = T>,
+        T: Shifter<T>,
+    {
+        Self::check_amounts(&[A, B, C]);
+        self.hash.scramble_funcs.σ1 = Scramble::<T>::σ::<A, B, C>;
+        self
+    }
+
+    // Σ0(X) = RotR(X,A) ⊕ RotR(X,B) ⊕ RotR(X,C), used by the rounds
+    pub fn with_Σ0<const A: u8, const B: u8, const C: u8>(mut self) -> Self
+    where
+        T: BitAnd<Output = T>,
+        T: BitXor<Output = T>,
+        T: Shifter<T>,
+    {
+        Self::check_amounts(&[A, B, C]);
+        self.hash.scramble_funcs.Σ0 = Scramble::<T>::Σ::<A, B, C>;
+        self
+    }
+
+    // Σ1(X) = RotR(X,A) ⊕ RotR(X,B) ⊕ RotR(X,C), used by the rounds
+    pub fn with_Σ1<const A: u8, const B: u8, const C: u8>(mut self) -> Self
+    where
+        T: BitAnd<Output = T>,
+        T: BitXor<Output = T>,
+        T: Shifter<T>,
+    {
+        Self::check_amounts(&[A, B, C]);
+        self.hash.scramble_funcs.Σ1 = Scramble::<T>::Σ::<A, B, C>;
+        self
+    }
+
+    // rotating or shifting by the word size or more overflows
+    fn check_amounts(amounts: &[u8]) {
+        let bits = 8 * core::mem::size_of::<T>();
+        assert!(
+            amounts.iter().all(|&n| (n as usize) < bits),
+            "rotation and shift amounts must be less than {} bits",
+            bits
+        );
+    }
+
+    // even with the standard parameters, the hash is marked as non-standard
+    pub fn build(mut self) -> Hash<T, BLOCKSIZE, ROUNDS> {
+        self.hash.standard = false;
+        self.hash
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::{Sha256, IV};
+    #[cfg(feature = "std")]
+    use crate::{sha512::Sha512, trace::Recorder};
+
+    fn hex_digest<const BLOCKSIZE: usize, const ROUNDS: usize, T>(
+        mut sha: Hash<T, BLOCKSIZE, ROUNDS>,
+        msg: &[u8],
+    ) -> String
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        sha.update(msg);
+        sha.finalize();
+        let mut digest = [0u8; 64];
+        let digest = &mut digest[..sha.digest_size];
+        sha.digest_into(digest);
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn standard_parameters() {
+        let sha256 = Sha2Builder::new(Sha256::new())
+            .with_rounds(64)
+            .with_σ0::<7, 18, 3>()
+            .with_Σ1::<6, 11, 25>()
+            .build();
+        assert!(!sha256.standard);
+        assert!(Sha256::new().standard);
+        assert_eq!(
+            hex_digest(sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    // SHA-224 is SHA-256 with another IV, truncated to 28 bytes
+    #[test]
+    fn custom_iv() {
+        let sha224 = Sha2Builder::new(Sha256::new())
+            .with_iv([
+                0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7,
+                0xbefa4fa4,
+            ])
+            .with_digest_size(28)
+            .build();
+        assert_eq!(
+            hex_digest(sha224, b"abc"),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+    }
+
+    #[test]
+    fn reduced_rounds() {
+        // without any round, each word of H(0) is added to itself
+        let mut sha256 = Sha2Builder::new(Sha256::new()).with_rounds(0).build();
+        sha256.update(b"abc");
+        sha256.finalize();
+        for (h, iv) in sha256.hash.iter().zip(IV.iter()) {
+            assert_eq!(*h, iv.wrapping_add(*iv));
+        }
+    }
+
+    // the first rounds are those of the full hash
+    #[test]
+    #[cfg(feature = "std")]
+    fn reduced_rounds_trace() {
+        let mut full = Recorder::default();
+        Sha512::new().trace(b"abc", &mut full);
+        let mut reduced = Recorder::default();
+        Sha2Builder::new(Sha512::new())
+            .with_rounds(24)
+            .build()
+            .trace(b"abc", &mut reduced);
+        assert_eq!(reduced.blocks[0].rounds.len(), 24);
+        assert_eq!(reduced.blocks[0].rounds[..], full.blocks[0].rounds[..24]);
+        assert_ne!(reduced.blocks[0].hash, full.blocks[0].hash);
+    }
+
+    #[test]
+    fn custom_functions() {
+        let mut k_constants = Sha256::k_constants();
+        k_constants[63] ^= 1;
+        let sha256 = Sha2Builder::new(Sha256::new())
+            .with_k_constants(k_constants)
+            .build();
+        assert_ne!(
+            hex_digest(sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        let sha256 = Sha2Builder::new(Sha256::new())
+            .with_Σ0::<2, 13, 21>()
+            .build();
+        assert_ne!(
+            hex_digest(sha256, b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn too_many_rounds() {
+        Sha2Builder::new(Sha256::new()).with_rounds(65);
+    }
+
+    #[test]
+    #[should_panic(expected = "not a SHA-2 hash")]
+    fn sha1() {
+        Sha2Builder::new(crate::sha1::Sha1::new());
+    }
+
+    #[test]
+    #[should_panic(expected = "rotation and shift amounts must be less than 32 bits")]
+    fn shift_overflow() {
+        Sha2Builder::new(Sha256::new()).with_σ0::<7, 18, 32>();
+    }
+
+    #[test]
+    #[should_panic(expected = "the hash has already been updated")]
+    fn updated_hash() {
+        let mut sha256 = Sha256::new();
+        sha256.update(b"abc");
+        Sha2Builder::new(sha256);
+    }
+}
@@ -0,0 +1,168 @@
+use std::fmt::LowerHex;
+use std::io::{Error, ErrorKind};
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+
+// length-extension attack: the digest of an unknown message M is the hash value after hashing M
+// and its padding. Knowing only the length of M, a hash can then continue with more data and give
+// the digest of M || glue padding || data, e.g. to forge H(secret || msg) based MACs
+
+// forged suffix, to be appended to the original message, and its digest
+#[derive(Debug, Clone, PartialEq)]
+pub struct Extension {
+    pub suffix: Vec<u8>, // glue padding || appended data
+    pub digest: Vec<u8>,
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // padding added by finalize() to a message of length bytes
+    pub fn glue_padding(&self, length: u64) -> Vec<u8> {
+        let mut hash = self.fork();
+        let bytes_read = (length % BLOCKSIZE as u64) as usize;
+        let additional_block = hash.block_padding(bytes_read, length);
+
+        let mut padding = hash.block[bytes_read..].to_vec();
+        if let Some(new_block) = additional_block {
+            padding.extend_from_slice(&new_block);
+        }
+        padding
+    }
+
+    // continue from the digest of a message of length bytes: the message hashed so far is then
+    // this message followed by its glue padding
+    pub fn resume_from_digest(&mut self, digest: &[u8], length: u64) -> std::io::Result<()> {
+        let size = std::mem::size_of::<T>();
+        if digest.len() != self.digest_size || !digest.len().is_multiple_of(size) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("digest should be {} bytes", self.digest_size),
+            ));
+        }
+
+        // a truncated digest, e.g. of SHA-384, doesn't give the whole state
+        if self.digest_size < self.state_words * size {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "truncated digests can't be extended",
+            ));
+        }
+
+        for (word, chunk) in self.hash.iter_mut().zip(digest.chunks(size)) {
+            *word = T::to_uint(chunk);
+        }
+        self.length = length + self.glue_padding(length).len() as u64;
+        self.block_len = 0;
+        Ok(())
+    }
+}
+
+// digest of original message || suffix, given the digest and the length of the original message.
+// hash is a fresh hash of the same variant, e.g.: extend(Sha256::new(), &digest, 64, b"data")
+pub fn extend<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    mut hash: Hash<T, BLOCKSIZE, ROUNDS>,
+    digest: &[u8],
+    length: u64,
+    data: &[u8],
+) -> std::io::Result<Extension>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    let mut suffix = hash.glue_padding(length);
+    suffix.extend_from_slice(data);
+
+    hash.resume_from_digest(digest, length)?;
+    hash.update(data);
+    hash.finalize();
+
+    Ok(Extension {
+        suffix,
+        digest: hash.digest_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    // the forged digest is the digest of secret || message || suffix
+    fn forge<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+        new: fn() -> Hash<T, BLOCKSIZE, ROUNDS>,
+    ) where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        let message = b"secret key user=guest";
+        for length in [
+            0,
+            21,
+            BLOCKSIZE - 9,
+            BLOCKSIZE - 8,
+            BLOCKSIZE,
+            3 * BLOCKSIZE + 1,
+        ] {
+            let message: Vec<u8> = message.iter().cycle().take(length).copied().collect();
+
+            let digest = new().digest_of(&message);
+
+            let extension = extend(new(), &digest, length as u64, b";admin=true").unwrap();
+            assert_eq!((length + extension.suffix.len() - 11) % BLOCKSIZE, 0);
+
+            let mut forged = new();
+            forged.update(&message);
+            forged.update(&extension.suffix);
+            forged.finalize();
+            let mut expected = vec![0u8; forged.digest_size];
+            forged.digest_into(&mut expected);
+            assert_eq!(extension.digest, expected);
+        }
+    }
+
+    #[test]
+    fn length_extension() {
+        forge(Sha1::new);
+        forge(Sha256::new);
+        forge(Sha512::new);
+    }
+
+    #[test]
+    fn glue_padding() {
+        let padding = Sha256::new().glue_padding(3);
+        assert_eq!(padding.len(), 61);
+        assert_eq!(padding[0], 0x80);
+        assert_eq!(padding[60], 24);
+
+        // no room left for the length: an additional block is needed
+        assert_eq!(Sha256::new().glue_padding(56).len(), 72);
+        assert_eq!(Sha512::new().glue_padding(112).len(), 144);
+    }
+
+    #[test]
+    fn invalid_digest() {
+        assert!(extend(Sha256::new(), &[0u8; 31], 3, b"").is_err());
+        assert!(Sha512::new().resume_from_digest(&[0u8; 32], 3).is_err());
+
+        // truncated digests
+        let mut sha384 = Sha512::sha384();
+        let error = sha384.resume_from_digest(&[0u8; 48], 3).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidInput);
+        let mut truncated = crate::experimental::Sha2Builder::new(Sha256::new())
+            .with_digest_size(28)
+            .build();
+        assert!(truncated.resume_from_digest(&[0u8; 28], 3).is_err());
+    }
+}
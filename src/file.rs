@@ -0,0 +1,166 @@
+use std::fmt::LowerHex;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::convert::Modular;
+use crate::sha::{Digest, Hash};
+
+// how the file content is fed to the hash
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReadMode {
+    Buffered,
+    Mmap,
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS> {
+    // hash a whole file, either memory-mapped or read by chunks of buffer_size bytes
+    pub fn file_hash<P: AsRef<Path>>(&mut self, path: P, mode: ReadMode) -> std::io::Result<()>
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        let buffer_size = self.buffer_size;
+        digest_file(self, path, mode, buffer_size)
+    }
+
+    // digest of a whole file, e.g.: Sha256::new().file_digest(path, ReadMode::Mmap)
+    pub fn file_digest<P: AsRef<Path>>(
+        mut self,
+        path: P,
+        mode: ReadMode,
+    ) -> std::io::Result<Vec<u8>>
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        self.file_hash(path, mode)?;
+        Ok(self.digest_vec())
+    }
+}
+
+// same as file_hash() for any digest, e.g. the SHA-3 family
+pub fn digest_file<D: Digest, P: AsRef<Path>>(
+    digest: &mut D,
+    path: P,
+    mode: ReadMode,
+    buffer_size: usize,
+) -> std::io::Result<()> {
+    let file = File::open(path)?;
+
+    if mode == ReadMode::Mmap && mmap_hash(digest, &file)? {
+        return Ok(());
+    }
+
+    buffered_hash(digest, file, buffer_size)
+}
+
+// the whole file is seen as a single slice. Returns false if the file can't be mapped
+#[cfg(feature = "mmap")]
+fn mmap_hash<D: Digest>(digest: &mut D, file: &File) -> std::io::Result<bool> {
+    // empty files can't be mapped on every platform
+    if file.metadata()?.len() == 0 {
+        return Ok(false);
+    }
+
+    // safety: the file must not be truncated while it's mapped, as for any memory-mapped file
+    let map = match unsafe { memmap2::Mmap::map(file) } {
+        Ok(map) => map,
+        Err(_) => return Ok(false),
+    };
+
+    digest.update(&map);
+    digest.finalize();
+    Ok(true)
+}
+
+#[cfg(not(feature = "mmap"))]
+fn mmap_hash<D: Digest>(_digest: &mut D, _file: &File) -> std::io::Result<bool> {
+    Ok(false)
+}
+
+// fallback when memory mapping is not possible: large reads, each one hashing many blocks
+fn buffered_hash<D: Digest>(
+    digest: &mut D,
+    mut file: File,
+    buffer_size: usize,
+) -> std::io::Result<()> {
+    let mut buffer = vec![0u8; buffer_size];
+
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(bytes_read) => digest.update(&buffer[..bytes_read]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    digest.finalize();
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha::DEFAULT_BUFFER_SIZE;
+    use crate::sha256::Sha256;
+    use crate::sha3::Sha3;
+    use crate::sha512::Sha512;
+    use std::io::{BufReader, Cursor, Write};
+
+    #[test]
+    fn same_as_reader() {
+        // various sizes around block boundaries, and more than the read buffer
+        for &size in &[0usize, 3, 64, 127, 128, 1000, DEFAULT_BUFFER_SIZE + 17] {
+            let data: Vec<u8> = (0..size).map(|i| (i % 251) as u8).collect();
+
+            // unique to this process, as test binaries may run concurrently
+            let path =
+                std::env::temp_dir().join(format!("sha_file_test_{}_{}", std::process::id(), size));
+            let mut file = File::create(&path).unwrap();
+            file.write_all(&data).unwrap();
+            drop(file);
+
+            let mut reader = Sha256::new();
+            let _ = reader.message_hash(size as u64, BufReader::new(Cursor::new(&data)));
+
+            for &mode in &[ReadMode::Buffered, ReadMode::Mmap] {
+                let mut sha256 = Sha256::new();
+                assert!(sha256.file_hash(&path, mode).is_ok());
+                assert_eq!(sha256.to_string(), reader.to_string());
+            }
+
+            let mut reader = Sha512::new();
+            let _ = reader.message_hash(size as u64, BufReader::new(Cursor::new(&data)));
+
+            for &mode in &[ReadMode::Buffered, ReadMode::Mmap] {
+                let mut sha512 = Sha512::new();
+                assert!(sha512.file_hash(&path, mode).is_ok());
+                assert_eq!(sha512.to_string(), reader.to_string());
+            }
+
+            for &mode in &[ReadMode::Buffered, ReadMode::Mmap] {
+                let mut sha3 = Sha3::sha3_256();
+                assert!(digest_file(&mut sha3, &path, mode, 1000).is_ok());
+
+                let mut expected = Sha3::sha3_256();
+                expected.update(&data);
+                expected.finalize();
+                assert_eq!(sha3.to_string(), expected.to_string());
+            }
+
+            std::fs::remove_file(&path).unwrap();
+        }
+    }
+
+    #[test]
+    fn file_not_found() {
+        let mut sha256 = Sha256::new();
+        assert!(sha256.file_hash("/foo/bar", ReadMode::Mmap).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+use core::fmt::{Display, LowerHex};
+
+use crate::convert::Modular;
+use crate::sha::{Digest, Hash};
+
+// HMAC (FIPS 198-1) over any Hash variant: the hash given to new() is only used as a template
+#[derive(Clone)]
+pub struct Hmac<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
+    inner: Hash<T, BLOCKSIZE, ROUNDS>,
+    outer: Hash<T, BLOCKSIZE, ROUNDS>, // already holds the key xored with opad
+}
+
+const IPAD: u8 = 0x36;
+const OPAD: u8 = 0x5c;
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hmac<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // e.g.: Hmac::new(Sha256::new(), key)
+    pub fn new(hash: Hash<T, BLOCKSIZE, ROUNDS>, key: &[u8]) -> Self {
+        // keys longer than a block are hashed first
+        let mut padded_key = [0u8; BLOCKSIZE];
+        if key.len() > BLOCKSIZE {
+            let mut key_hash = hash.fork();
+            key_hash.update(key);
+            key_hash.finalize();
+            key_hash.digest_into(&mut padded_key[..hash.digest_size]);
+        } else {
+            padded_key[..key.len()].copy_from_slice(key);
+        }
+
+        let mut inner = hash.fork();
+        let mut outer = hash;
+        let mut pad = [0u8; BLOCKSIZE];
+
+        for (p, k) in pad.iter_mut().zip(padded_key.iter()) {
+            *p = k ^ IPAD;
+        }
+        inner.update(&pad);
+
+        for (p, k) in pad.iter_mut().zip(padded_key.iter()) {
+            *p = k ^ OPAD;
+        }
+        outer.update(&pad);
+
+        Hmac { inner, outer }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.inner.update(data);
+    }
+
+    pub fn finalize(&mut self) {
+        self.inner.finalize();
+
+        // the digest is at most 8 words of 8 bytes
+        let mut inner_digest = [0u8; 64];
+        let inner_digest = &mut inner_digest[..self.inner.digest_size];
+        self.inner.digest_into(inner_digest);
+
+        self.outer.update(inner_digest);
+        self.outer.finalize();
+    }
+
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.outer.digest_into(out);
+    }
+
+    pub fn digest<const N: usize>(&self) -> [u8; N] {
+        self.outer.digest()
+    }
+
+    // compare a received tag with the finalized MAC, without stopping at the first difference
+    pub fn verify(&self, tag: &[u8]) -> bool {
+        let mut mac = [0u8; 64];
+        let mac = &mut mac[..self.outer.digest_size];
+        self.outer.digest_into(mac);
+
+        tag.len() == mac.len()
+            && tag
+                .iter()
+                .zip(mac.iter())
+                .fold(0, |diff, (a, b)| diff | (a ^ b))
+                == 0
+    }
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Digest for Hmac<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn update(&mut self, data: &[u8]) {
+        Hmac::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        Hmac::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        Hmac::digest_into(self, out)
+    }
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Display for Hmac<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+    T: Display,
+{
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.outer.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    // RFC 2202 and RFC 4231 test cases 1, 2 and 6
+    const KEYS: [&[u8]; 3] = [&[0x0b; 20], b"Jefe", &[0xaa; 131]];
+    const DATA: [&[u8]; 3] = [
+        b"Hi There",
+        b"what do ya want for nothing?",
+        b"Test Using Larger Than Block-Size Key - Hash Key First",
+    ];
+
+    #[test]
+    fn hmac_sha1() {
+        let expected = [
+            "b617318655057264e28bc0b6fb378c8ef146be00",
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+            "90d0dace1c1bdc957339307803160335bde6df2b",
+        ];
+        for i in 0..3 {
+            let mut hmac = Hmac::new(Sha1::new(), KEYS[i]);
+            hmac.update(DATA[i]);
+            hmac.finalize();
+            assert_eq!(hmac.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn hmac_sha256() {
+        let expected = [
+            "b0344c61d8db38535ca8afceaf0bf12b881dc200c9833da726e9376c2e32cff7",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+            "60e431591ee0b67f0d8a26aacbf5b77f8e0bc6213728c5140546040f0ee37f54",
+        ];
+        for i in 0..3 {
+            let mut hmac = Hmac::new(Sha256::new(), KEYS[i]);
+            hmac.update(DATA[i]);
+            hmac.finalize();
+            assert_eq!(hmac.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn hmac_sha512() {
+        let expected = [
+            "87aa7cdea5ef619d4ff0b4241a1d6cb02379f4e2ce4ec2787ad0b30545e17cdedaa833b7d6b8a702038b274eaea3f4e4be9d914eeb61f1702e696c203a126854",
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+            "80b24263c7c1a3ebb71493c1dd7be8b49b46d1f41b4aeec1121b013783f8f3526b56d037e05f2598bd0fd2215d6a1e5295e64f73f63f0aec8b915a985d786598",
+        ];
+        for i in 0..3 {
+            let mut hmac = Hmac::new(Sha512::new(), KEYS[i]);
+            hmac.update(DATA[i]);
+            hmac.finalize();
+            assert_eq!(hmac.to_string(), expected[i]);
+        }
+    }
+
+    #[test]
+    fn verify() {
+        let mut hmac = Hmac::new(Sha256::new(), b"key");
+        hmac.update(b"message");
+        hmac.finalize();
+
+        let mut tag: [u8; 32] = hmac.digest();
+        assert!(hmac.verify(&tag));
+        assert!(!hmac.verify(&tag[..16]));
+        tag[0] ^= 0x80;
+        assert!(!hmac.verify(&tag));
+    }
+}
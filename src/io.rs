@@ -0,0 +1,197 @@
+use std::fmt::LowerHex;
+use std::io::{Read, Write};
+
+use crate::convert::Modular;
+use crate::sha::{Digest, Hash};
+use crate::sha3::Sha3;
+
+// any writer-based producer can feed the hash, e.g.: io::copy(&mut reader, &mut sha256)
+// finalize() must be called once all data is written
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Write for Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Write for Sha3 {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+// reader adaptor: every byte read from the inner reader is also hashed
+pub struct HashingReader<R, H> {
+    inner: R,
+    hash: H,
+}
+
+impl<R: Read, H: Digest> HashingReader<R, H> {
+    pub fn new(inner: R, hash: H) -> Self {
+        HashingReader { inner, hash }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn hash(&self) -> &H {
+        &self.hash
+    }
+
+    // finalize the hash and give back the inner reader
+    pub fn finalize(mut self) -> (R, H) {
+        self.hash.finalize();
+        (self.inner, self.hash)
+    }
+}
+
+impl<R: Read, H: Digest> Read for HashingReader<R, H> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes_read = self.inner.read(buf)?;
+        self.hash.update(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+// writer adaptor: every byte accepted by the inner writer is also hashed
+pub struct HashingWriter<W, H> {
+    inner: W,
+    hash: H,
+}
+
+impl<W: Write, H: Digest> HashingWriter<W, H> {
+    pub fn new(inner: W, hash: H) -> Self {
+        HashingWriter { inner, hash }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn hash(&self) -> &H {
+        &self.hash
+    }
+
+    // finalize the hash and give back the inner writer
+    pub fn finalize(mut self) -> (W, H) {
+        self.hash.finalize();
+        (self.inner, self.hash)
+    }
+}
+
+impl<W: Write, H: Digest> Write for HashingWriter<W, H> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        // only what the inner writer has accepted is hashed
+        let bytes_written = self.inner.write(buf)?;
+        self.hash.update(&buf[..bytes_written]);
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+    use std::io::Cursor;
+
+    #[test]
+    fn copy() {
+        let msg = [b'a'; 1_000_000];
+
+        let mut sha256 = Sha256::new();
+        let copied = std::io::copy(&mut Cursor::new(&msg[..]), &mut sha256);
+        assert_eq!(copied.unwrap(), 1_000_000);
+        sha256.finalize();
+        assert_eq!(
+            sha256.to_string(),
+            "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
+        );
+
+        let mut sha512 = Sha512::new();
+        let copied = std::io::copy(&mut Cursor::new(&msg[..]), &mut sha512);
+        assert_eq!(copied.unwrap(), 1_000_000);
+        sha512.finalize();
+        assert_eq!(
+            sha512.to_string(),
+            "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
+        );
+
+        let mut sha3 = Sha3::sha3_256();
+        let copied = std::io::copy(&mut Cursor::new(&msg[..]), &mut sha3);
+        assert_eq!(copied.unwrap(), 1_000_000);
+        sha3.finalize();
+        assert_eq!(
+            sha3.to_string(),
+            "5c8875ae474a3634ba4fd55ec85bffd661f32aca75c6d699d0cdcb6c115891c1"
+        );
+    }
+
+    #[test]
+    fn hashing_reader() {
+        let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+        let mut reader = HashingReader::new(Cursor::new(&msg[..]), Sha256::new());
+        let mut copy = Vec::new();
+        assert!(reader.read_to_end(&mut copy).is_ok());
+
+        let (cursor, sha256) = reader.finalize();
+        assert_eq!(copy, &msg[..]);
+        assert_eq!(cursor.position(), 56);
+        assert_eq!(
+            sha256.to_string(),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+    }
+
+    #[test]
+    fn hashing_writer() {
+        let msg = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
+
+        let mut writer = HashingWriter::new(Vec::new(), Sha512::new());
+        for chunk in msg.chunks(10) {
+            assert!(writer.write_all(chunk).is_ok());
+        }
+        assert!(writer.flush().is_ok());
+
+        let (v, sha512) = writer.finalize();
+        assert_eq!(v, &msg[..]);
+        assert_eq!(
+            sha512.to_string(),
+            "8e959b75dae313da8cf4f72814fc143f8f7779c6eb9f7fa17299aeadb6889018501d289e4900f7e4331b99dec4b5433ac7d329eeb6dd26545e96e55b874be909"
+        );
+    }
+
+    #[test]
+    fn formatted_write() {
+        let (ab, c) = ("ab", 'c');
+        let mut sha256 = Sha256::new();
+        assert!(write!(sha256, "{}{}", ab, c).is_ok());
+        assert!(sha256.flush().is_ok());
+        sha256.finalize();
+        assert_eq!(
+            sha256.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+}
@@ -1,5 +1,39 @@
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+#[cfg(feature = "async")]
+pub mod async_io;
+pub mod bitcoin;
+#[cfg(feature = "std")]
+pub mod cavp;
 pub mod convert;
+#[cfg(feature = "std")]
+pub mod crypt;
+pub mod drbg;
+pub mod experimental;
+#[cfg(feature = "std")]
+pub mod extension;
+#[cfg(feature = "std")]
+pub mod file;
+pub mod hmac;
+#[cfg(feature = "std")]
+pub mod io;
+#[cfg(feature = "std")]
+pub mod merkle;
+pub mod mgf1;
+#[cfg(feature = "std")]
+pub mod oci;
 pub mod scramble;
+pub mod selftest;
 pub mod sha;
+pub mod sha1;
 pub mod sha256;
+pub mod sha3;
 pub mod sha512;
+pub mod sp800_185;
+#[cfg(feature = "std")]
+pub mod sri;
+#[cfg(feature = "std")]
+pub mod state;
+pub mod trace;
+#[cfg(feature = "std")]
+pub mod vectors;
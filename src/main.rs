@@ -1,23 +1,35 @@
 use std::env;
-use std::fs::File;
-use std::io::BufReader;
+use std::fmt::{Display, LowerHex};
 use std::str::FromStr;
 
-mod convert;
-mod scramble;
-#[allow(non_upper_case_globals)]
-mod sha;
-
-mod sha256;
-use sha256::Sha256;
-
-mod sha512;
-use sha512::Sha512;
+use sha::cavp::{from_hex, to_hex};
+use sha::convert::Modular;
+use sha::crypt::{crypt, encode_salt, verify, SALT_MAX};
+use sha::experimental::Sha2Builder;
+use sha::extension::extend;
+use sha::file::{digest_file, ReadMode};
+use sha::oci::{validate_layout, OciAlgorithm, OciDigest};
+use sha::selftest::known_answer_tests;
+use sha::sha::{Hash, DEFAULT_BUFFER_SIZE};
+use sha::sha1::Sha1;
+use sha::sha256::Sha256;
+use sha::sha3::Sha3;
+use sha::sha512::Sha512;
+use sha::sri::{Integrity, SriAlgorithm};
+use sha::trace::Recorder;
+use sha::vectors::verify_file;
 
 #[derive(PartialEq)]
 enum ShaVersion {
+    Sha1,
     Sha256,
     Sha512,
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    Shake128,
+    Shake256,
 }
 
 impl FromStr for ShaVersion {
@@ -25,36 +37,491 @@ impl FromStr for ShaVersion {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "1" => Ok(ShaVersion::Sha1),
             "256" => Ok(ShaVersion::Sha256),
             "512" => Ok(ShaVersion::Sha512),
-            _ => unimplemented!("valid sha version is 256 or 512"),
+            "3-224" => Ok(ShaVersion::Sha3_224),
+            "3-256" => Ok(ShaVersion::Sha3_256),
+            "3-384" => Ok(ShaVersion::Sha3_384),
+            "3-512" => Ok(ShaVersion::Sha3_512),
+            "shake128" => Ok(ShaVersion::Shake128),
+            "shake256" => Ok(ShaVersion::Shake256),
+            _ => unimplemented!(
+                "valid sha version is 1, 256, 512, 3-224, 3-256, 3-384, 3-512, shake128 or shake256"
+            ),
+        }
+    }
+}
+
+const USAGE: &str = "usage: sha [--mmap] [--resume-state <state file>] [--detect-collisions] \
+    [--length <bytes>] [--trace|--trace-json] [--rounds <n>] <1|256|512|3-224|3-256|3-384|3-512|shake128|shake256> <file>
+       sha [--mmap] --sri <256|384|512> <file>
+       sha extend <1|256|512> <digest> <message length> <file to append>
+       sha crypt <5|6|$5$[rounds=<n>$]<salt>|$6$[rounds=<n>$]<salt>> [password]
+       sha crypt verify <crypt string> [password]
+       sha [--mmap] oci <256|512> <file>
+       sha [--mmap] oci check <sha256:<hex>|sha512:<hex>> <file>
+       sha [--mmap] oci layout <image layout directory>
+       sha verify-vectors <file.rsp|acvp.json>
+       sha selftest";
+
+// intermediate values of the SHA-2 rounds
+#[derive(Clone, Copy, PartialEq)]
+enum TraceFormat {
+    Table,
+    Json,
+}
+
+// command line options
+struct Options {
+    mode: ReadMode,
+    state_file: Option<String>,
+    detect_collisions: bool,
+    length: Option<usize>,
+    trace: Option<TraceFormat>,
+    rounds: Option<usize>,
+    sri: bool,
+}
+
+// experimental: SHA-2 reduced to the --rounds number of rounds, which is not SHA-2 anymore
+fn reduced<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    sha: Hash<T, BLOCKSIZE, ROUNDS>,
+    options: &Options,
+) -> Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    match options.rounds {
+        Some(rounds) => {
+            if rounds > ROUNDS {
+                eprintln!("--rounds must be at most {}", ROUNDS);
+                std::process::exit(1);
+            }
+            eprintln!(
+                "warning: non-standard hash reduced to {} of {} rounds",
+                rounds, ROUNDS
+            );
+            Sha2Builder::new(sha).with_rounds(rounds).build()
+        }
+        None => sha,
+    }
+}
+
+// calculate hash, resuming from the state file if any
+fn print_hash<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    mut sha: Hash<T, BLOCKSIZE, ROUNDS>,
+    file_name: &str,
+    options: &Options,
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+    T: Display,
+{
+    match &options.state_file {
+        Some(state_file) => sha.resumable_file_hash(file_name, state_file),
+        None => sha.file_hash(file_name, options.mode),
+    }
+    .unwrap_or_else(|e| panic!("unable to hash file {}: {}", file_name, e));
+
+    if sha.collision_detected {
+        eprintln!("warning: {} contains a SHA-1 collision attack", file_name);
+    }
+    println!("{}", sha);
+}
+
+// SHA-2 only: print the values of each round, then the hash except for JSON
+fn print_trace<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    mut sha: Hash<T, BLOCKSIZE, ROUNDS>,
+    file_name: &str,
+    format: TraceFormat,
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+    T: Display,
+{
+    let message = std::fs::read(file_name)
+        .unwrap_or_else(|e| panic!("unable to read file {}: {}", file_name, e));
+
+    let mut recorder = Recorder::default();
+    sha.trace(&message, &mut recorder);
+
+    match format {
+        TraceFormat::Table => {
+            print!("{}", recorder.to_table());
+            println!("{}", sha);
+        }
+        TraceFormat::Json => println!("{}", recorder.to_json()),
+    }
+}
+
+// SHA-3 family: no resumable state
+fn print_sha3(mut sha3: Sha3, file_name: &str, options: &Options) {
+    if options.state_file.is_some() {
+        eprintln!("--resume-state is only supported for SHA-1 and SHA-2");
+        std::process::exit(1);
+    }
+
+    digest_file(&mut sha3, file_name, options.mode, DEFAULT_BUFFER_SIZE)
+        .unwrap_or_else(|e| panic!("unable to hash file {}: {}", file_name, e));
+    println!("{}", sha3);
+}
+
+// length-extension attack: print the suffix to append to the original message and the new digest
+fn length_extension<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    sha: Hash<T, BLOCKSIZE, ROUNDS>,
+    digest: &str,
+    length: &str,
+    file_name: &str,
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    let digest = from_hex(digest).expect("digest must be an hex string");
+    let length = length
+        .parse()
+        .expect("message length must be a number of bytes");
+    let data = std::fs::read(file_name)
+        .unwrap_or_else(|e| panic!("unable to read file {}: {}", file_name, e));
+
+    let extension = extend(sha, &digest, length, &data)
+        .unwrap_or_else(|e| panic!("unable to extend {}: {}", to_hex(&digest), e));
+    println!("suffix: {}", to_hex(&extension.suffix));
+    println!("digest: {}", to_hex(&extension.digest));
+}
+
+// password given on the command line, or else the first line of the standard input
+fn read_password(password: Option<&String>) -> String {
+    match password {
+        Some(password) => password.clone(),
+        None => {
+            let mut line = String::new();
+            std::io::stdin()
+                .read_line(&mut line)
+                .expect("unable to read the password");
+            line.trim_end_matches(&['\r', '\n'][..]).to_string()
+        }
+    }
+}
+
+// random salt of the maximum length
+#[cfg(unix)]
+fn random_salt() -> std::io::Result<String> {
+    let mut random = [0u8; SALT_MAX];
+    let mut urandom = std::fs::File::open("/dev/urandom")?;
+    std::io::Read::read_exact(&mut urandom, &mut random)?;
+    Ok(encode_salt(&random))
+}
+
+// without /dev/urandom, the salt must be given
+#[cfg(not(unix))]
+fn random_salt() -> std::io::Result<String> {
+    Err(std::io::Error::new(
+        std::io::ErrorKind::Unsupported,
+        "no random source on this platform, give the salt, e.g. $5$<salt>",
+    ))
+}
+
+// SHA-crypt password hash, or its verification: the exit code is then 1 if the password is wrong
+fn crypt_command(args: &[String]) {
+    match args.first().map(String::as_str) {
+        Some("verify") if args.len() == 2 || args.len() == 3 => {
+            let password = read_password(args.get(2));
+            let valid = verify(password.as_bytes(), &args[1]);
+            println!("{}", if valid { "OK" } else { "FAILED" });
+            if !valid {
+                std::process::exit(1);
+            }
+        }
+        Some(setting) if args.len() <= 2 => {
+            let setting = match setting {
+                "5" | "6" => match random_salt() {
+                    Ok(salt) => format!("${}${}", setting, salt),
+                    Err(e) => {
+                        eprintln!("unable to generate a salt: {}", e);
+                        std::process::exit(1);
+                    }
+                },
+                _ => setting.to_string(),
+            };
+            let password = read_password(args.get(1));
+            match crypt(password.as_bytes(), &setting) {
+                Ok(crypt_string) => println!("{}", crypt_string),
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        }
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(1);
+        }
+    }
+}
+
+// OCI digests: print one, check a blob against one, or validate the blobs of an image layout. The
+// exit code is 1 if any check failed
+fn oci_command(args: &[String], mode: ReadMode) {
+    let fail = |msg: String| -> ! {
+        eprintln!("{}", msg);
+        std::process::exit(1);
+    };
+
+    match args {
+        [command, digest, file_name] if command == "check" => {
+            let digest: OciDigest = digest.parse().unwrap_or_else(|e| fail(format!("{}", e)));
+            let valid = digest
+                .verify_blob(file_name, mode)
+                .unwrap_or_else(|e| fail(format!("unable to hash file {}: {}", file_name, e)));
+            println!("{}: {}", file_name, if valid { "OK" } else { "FAILED" });
+            if !valid {
+                std::process::exit(1);
+            }
+        }
+        [command, dir] if command == "layout" => {
+            let report = validate_layout(dir, mode)
+                .unwrap_or_else(|e| fail(format!("unable to validate {}: {}", dir, e)));
+            for blob in &report.failed {
+                println!("FAILED {}", blob);
+            }
+            for digest in &report.missing {
+                println!("MISSING {}", digest);
+            }
+            println!("{}", report);
+            if !report.is_success() {
+                std::process::exit(1);
+            }
+        }
+        [version, file_name] => {
+            let algorithm = match version.as_str() {
+                "256" => OciAlgorithm::Sha256,
+                "512" => OciAlgorithm::Sha512,
+                _ => fail("OCI digests are sha256 or sha512".to_string()),
+            };
+            let digest = OciDigest::of_file(algorithm, file_name, mode)
+                .unwrap_or_else(|e| fail(format!("unable to hash file {}: {}", file_name, e)));
+            println!("{}", digest);
+        }
+        _ => fail(USAGE.to_string()),
+    }
+}
+
+// Subresource Integrity: the value of the integrity attribute of a script or stylesheet
+fn print_sri(version: &str, file_name: &str, options: &Options) {
+    let algorithm = match version {
+        "256" => SriAlgorithm::Sha256,
+        "384" => SriAlgorithm::Sha384,
+        "512" => SriAlgorithm::Sha512,
+        _ => {
+            eprintln!("SRI hashes are sha256, sha384 or sha512");
+            std::process::exit(1);
         }
+    };
+    if options.state_file.is_some() || options.trace.is_some() || options.rounds.is_some() {
+        eprintln!("--sri can't be used with --resume-state, --trace or --rounds");
+        std::process::exit(1);
+    }
+
+    let integrity = Integrity::of_file(algorithm, file_name, options.mode)
+        .unwrap_or_else(|e| panic!("unable to hash file {}: {}", file_name, e));
+    println!("{}", integrity);
+}
+
+// run a CAVP or ACVP test vector file, the exit code is 1 if any test failed
+fn verify_vectors(file_name: &str) {
+    let report =
+        verify_file(file_name).unwrap_or_else(|e| panic!("unable to verify {}: {}", file_name, e));
+
+    for id in &report.failed {
+        println!("FAILED {}", id);
+    }
+    println!("{}", report);
+
+    if !report.is_success() {
+        std::process::exit(1);
+    }
+}
+
+// run every known-answer test, the exit code is 1 if any test failed
+fn selftest() {
+    let mut success = true;
+
+    for kat in known_answer_tests() {
+        println!(
+            "{}: {}",
+            kat.algorithm,
+            if kat.passed { "passed" } else { "FAILED" }
+        );
+        success &= kat.passed;
+    }
+
+    if !success {
+        std::process::exit(1);
     }
 }
 
 fn main() {
-    let args: Vec<String> = env::args().collect();
-
-    // either 512 or 256
-    let version = ShaVersion::from_str(&args[1]).unwrap();
-
-    // create reader
-    let file = File::open(&args[2]).expect(&format!("unable to open file {}", &args[2]));
-    let reader = BufReader::new(file);
-
-    // get file length
-    let metadata = std::fs::metadata(&args[2])
-        .expect(&format!("unable to get metadata for file {}", &args[2]));
-    let file_length = metadata.len();
-
-    // calculate hash
-    if version == ShaVersion::Sha256 {
-        let mut sha = Sha256::new();
-        let _ = sha.message_hash(file_length, reader);
-        println!("{}", sha);
-    } else {
-        let mut sha = Sha512::new();
-        let _ = sha.message_hash(file_length, reader);
-        println!("{}", sha);
+    let mut options = Options {
+        mode: ReadMode::Buffered,
+        state_file: None,
+        detect_collisions: false,
+        length: None,
+        trace: None,
+        rounds: None,
+        sri: false,
+    };
+    let mut args: Vec<String> = Vec::new();
+
+    // options start with --, others are the sha version and the file name
+    let mut iter = env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--mmap" => options.mode = ReadMode::Mmap,
+            "--resume-state" => options.state_file = iter.next(),
+            "--detect-collisions" => options.detect_collisions = true,
+            "--length" => {
+                options.length = iter
+                    .next()
+                    .map(|l| l.parse().expect("--length must be a number of bytes"))
+            }
+            "--rounds" => {
+                options.rounds = iter
+                    .next()
+                    .map(|n| n.parse().expect("--rounds must be a number of rounds"))
+            }
+            "--trace" => options.trace = Some(TraceFormat::Table),
+            "--trace-json" => options.trace = Some(TraceFormat::Json),
+            "--sri" => options.sri = true,
+            _ if arg.starts_with("--") => {
+                eprintln!("unknown option {}\n{}", arg, USAGE);
+                std::process::exit(1);
+            }
+            _ => args.push(arg),
+        }
+    }
+
+    // a resumed hash reads the file by chunks, after the saved offset
+    if options.mode == ReadMode::Mmap && options.state_file.is_some() {
+        eprintln!("--mmap can't be used with --resume-state");
+        std::process::exit(1);
+    }
+
+    if args.len() == 1 && args[0] == "selftest" {
+        selftest();
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("crypt") {
+        crypt_command(&args[1..]);
+        return;
+    }
+
+    if args.first().map(String::as_str) == Some("oci") {
+        oci_command(&args[1..], options.mode);
+        return;
+    }
+
+    if args.len() == 5 && args[0] == "extend" {
+        let (digest, length, file_name) = (&args[2], &args[3], &args[4]);
+        match ShaVersion::from_str(&args[1]).unwrap() {
+            ShaVersion::Sha1 => length_extension(Sha1::new(), digest, length, file_name),
+            ShaVersion::Sha256 => length_extension(Sha256::new(), digest, length, file_name),
+            ShaVersion::Sha512 => length_extension(Sha512::new(), digest, length, file_name),
+            _ => {
+                eprintln!("the SHA-3 family is not vulnerable to length extension");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if args.len() != 2 {
+        eprintln!("{}", USAGE);
+        std::process::exit(1);
+    }
+
+    if options.sri {
+        print_sri(&args[0], &args[1], &options);
+        return;
+    }
+
+    if args[0] == "verify-vectors" {
+        verify_vectors(&args[1]);
+        return;
+    }
+
+    // SHA-1, SHA-2 or SHA-3 family
+    let version = ShaVersion::from_str(&args[0]).unwrap();
+    let file_name = &args[1];
+
+    if options.detect_collisions && version != ShaVersion::Sha1 {
+        eprintln!("--detect-collisions is only supported for SHA-1");
+        std::process::exit(1);
+    }
+
+    if options.length.is_some()
+        && version != ShaVersion::Shake128
+        && version != ShaVersion::Shake256
+    {
+        eprintln!("--length is only supported for SHAKE128 and SHAKE256");
+        std::process::exit(1);
+    }
+
+    if options.rounds.is_some() {
+        if version != ShaVersion::Sha256 && version != ShaVersion::Sha512 {
+            eprintln!("--rounds is only supported for SHA-256 and SHA-512");
+            std::process::exit(1);
+        }
+        if options.state_file.is_some() {
+            eprintln!("--rounds can't be used with --resume-state");
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(format) = options.trace {
+        if options.state_file.is_some() {
+            eprintln!("--trace can't be used with --resume-state");
+            std::process::exit(1);
+        }
+        match version {
+            ShaVersion::Sha256 => print_trace(reduced(Sha256::new(), &options), file_name, format),
+            ShaVersion::Sha512 => print_trace(reduced(Sha512::new(), &options), file_name, format),
+            _ => {
+                eprintln!("--trace is only supported for SHA-256 and SHA-512");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    match version {
+        ShaVersion::Sha1 if options.detect_collisions => {
+            print_hash(Sha1::new().with_collision_detection(), file_name, &options)
+        }
+        ShaVersion::Sha1 => print_hash(Sha1::new(), file_name, &options),
+        ShaVersion::Sha256 => print_hash(reduced(Sha256::new(), &options), file_name, &options),
+        ShaVersion::Sha512 => print_hash(reduced(Sha512::new(), &options), file_name, &options),
+        ShaVersion::Sha3_224 => print_sha3(Sha3::sha3_224(), file_name, &options),
+        ShaVersion::Sha3_256 => print_sha3(Sha3::sha3_256(), file_name, &options),
+        ShaVersion::Sha3_384 => print_sha3(Sha3::sha3_384(), file_name, &options),
+        ShaVersion::Sha3_512 => print_sha3(Sha3::sha3_512(), file_name, &options),
+        // SHAKE output defaults to twice the security strength
+        ShaVersion::Shake128 => {
+            let length = options.length.unwrap_or(32);
+            print_sha3(Sha3::shake128(length), file_name, &options)
+        }
+        ShaVersion::Shake256 => {
+            let length = options.length.unwrap_or(64);
+            print_sha3(Sha3::shake256(length), file_name, &options)
+        }
     }
 }
@@ -0,0 +1,453 @@
+use std::fmt::LowerHex;
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+
+// Merkle tree hash of RFC 6962 (Certificate Transparency): leaves and nodes are hashed with a
+// different prefix, so that a leaf can't be taken for a node
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+// the leaves are kept as their leaf hash, in the order they were pushed
+pub struct MerkleTree<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
+    hash: Hash<T, BLOCKSIZE, ROUNDS>,
+    leaves: Vec<Vec<u8>>,
+}
+
+// largest power of 2 smaller than n, n > 1: the size of the left subtree
+fn split(n: usize) -> usize {
+    let mut k = 1;
+    while k << 1 < n {
+        k <<= 1;
+    }
+    k
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> MerkleTree<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // empty tree, the hash given being a template, e.g.: MerkleTree::new(Sha256::new())
+    pub fn new(hash: Hash<T, BLOCKSIZE, ROUNDS>) -> Self {
+        MerkleTree {
+            hash,
+            leaves: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        let leaf = self.leaf_hash(data);
+        self.leaves.push(leaf);
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    fn digest(hash: &Hash<T, BLOCKSIZE, ROUNDS>, parts: &[&[u8]]) -> Vec<u8> {
+        let mut hash = hash.fork();
+        for part in parts {
+            hash.update(part);
+        }
+        hash.finalize();
+
+        hash.digest_vec()
+    }
+
+    // H(0x00 || data)
+    pub fn leaf_hash(&self, data: &[u8]) -> Vec<u8> {
+        Self::digest(&self.hash, &[&[LEAF_PREFIX], data])
+    }
+
+    // H(0x01 || left || right)
+    pub fn node_hash(&self, left: &[u8], right: &[u8]) -> Vec<u8> {
+        Self::node(&self.hash, left, right)
+    }
+
+    fn node(hash: &Hash<T, BLOCKSIZE, ROUNDS>, left: &[u8], right: &[u8]) -> Vec<u8> {
+        Self::digest(hash, &[&[NODE_PREFIX], left, right])
+    }
+
+    // MTH(D[n]): the hash of the empty string for an empty tree
+    fn subtree_root(&self, leaves: &[Vec<u8>]) -> Vec<u8> {
+        match leaves.len() {
+            0 => Self::digest(&self.hash, &[]),
+            1 => leaves[0].clone(),
+            n => {
+                let k = split(n);
+                let left = self.subtree_root(&leaves[..k]);
+                let right = self.subtree_root(&leaves[k..]);
+                self.node_hash(&left, &right)
+            }
+        }
+    }
+
+    pub fn root(&self) -> Vec<u8> {
+        self.subtree_root(&self.leaves)
+    }
+
+    // root of the tree when it had its first size leaves
+    pub fn root_at(&self, size: usize) -> Option<Vec<u8>> {
+        if size > self.leaves.len() {
+            return None;
+        }
+        Some(self.subtree_root(&self.leaves[..size]))
+    }
+
+    // PATH(m, D[n]): the sibling hashes from the leaf up to the root
+    fn subtree_path(&self, m: usize, leaves: &[Vec<u8>], path: &mut Vec<Vec<u8>>) {
+        let n = leaves.len();
+        if n <= 1 {
+            return;
+        }
+
+        let k = split(n);
+        if m < k {
+            self.subtree_path(m, &leaves[..k], path);
+            path.push(self.subtree_root(&leaves[k..]));
+        } else {
+            self.subtree_path(m - k, &leaves[k..], path);
+            path.push(self.subtree_root(&leaves[..k]));
+        }
+    }
+
+    // audit path of the index-th leaf in the current tree
+    pub fn audit_path(&self, index: usize) -> Option<Vec<Vec<u8>>> {
+        if index >= self.leaves.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        self.subtree_path(index, &self.leaves, &mut path);
+        Some(path)
+    }
+
+    // SUBPROOF(m, D[n], b)
+    fn subproof(&self, m: usize, leaves: &[Vec<u8>], complete: bool, proof: &mut Vec<Vec<u8>>) {
+        let n = leaves.len();
+        if m == n {
+            if !complete {
+                proof.push(self.subtree_root(leaves));
+            }
+            return;
+        }
+
+        let k = split(n);
+        if m <= k {
+            self.subproof(m, &leaves[..k], complete, proof);
+            proof.push(self.subtree_root(&leaves[k..]));
+        } else {
+            self.subproof(m - k, &leaves[k..], false, proof);
+            proof.push(self.subtree_root(&leaves[..k]));
+        }
+    }
+
+    // proof that the tree of old_size leaves is a prefix of the current tree
+    pub fn consistency_proof(&self, old_size: usize) -> Option<Vec<Vec<u8>>> {
+        if old_size > self.leaves.len() {
+            return None;
+        }
+
+        let mut proof = Vec::new();
+        if old_size > 0 {
+            self.subproof(old_size, &self.leaves, true, &mut proof);
+        }
+        Some(proof)
+    }
+
+    // the leaf hash at index is in the tree of tree_size leaves with this root (RFC 9162 2.1.3.2).
+    // No tree is needed, only a hash of its variant, e.g.:
+    // MerkleTree::verify_inclusion(&Sha256::new(), &leaf_hash, index, tree_size, &path, &root)
+    pub fn verify_inclusion(
+        hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+        leaf_hash: &[u8],
+        index: usize,
+        tree_size: usize,
+        path: &[Vec<u8>],
+        root: &[u8],
+    ) -> bool {
+        if index >= tree_size {
+            return false;
+        }
+
+        let (mut f, mut s) = (index, tree_size - 1);
+        let mut r = leaf_hash.to_vec();
+
+        for p in path {
+            if s == 0 {
+                return false;
+            }
+
+            if f & 1 == 1 || f == s {
+                r = Self::node(hash, p, &r);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                r = Self::node(hash, &r, p);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+
+        s == 0 && r == root
+    }
+
+    // the tree of old_size leaves and old_root is a prefix of the tree of new_size leaves and
+    // new_root (RFC 9162 2.1.4.2)
+    pub fn verify_consistency(
+        hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+        old_size: usize,
+        new_size: usize,
+        old_root: &[u8],
+        new_root: &[u8],
+        proof: &[Vec<u8>],
+    ) -> bool {
+        if old_size > new_size {
+            return false;
+        }
+        // an empty tree is a prefix of any tree
+        if old_size == 0 {
+            return proof.is_empty();
+        }
+        if old_size == new_size {
+            return proof.is_empty() && old_root == new_root;
+        }
+
+        // the old root is the first node of the proof when the old tree is a complete subtree
+        let mut nodes: Vec<&[u8]> = proof.iter().map(|p| p.as_slice()).collect();
+        if old_size.is_power_of_two() {
+            nodes.insert(0, old_root);
+        }
+        if nodes.is_empty() {
+            return false;
+        }
+
+        let (mut f, mut s) = (old_size - 1, new_size - 1);
+        while f & 1 == 1 {
+            f >>= 1;
+            s >>= 1;
+        }
+
+        let mut old_r = nodes[0].to_vec();
+        let mut new_r = nodes[0].to_vec();
+
+        for c in &nodes[1..] {
+            if s == 0 {
+                return false;
+            }
+
+            if f & 1 == 1 || f == s {
+                old_r = Self::node(hash, c, &old_r);
+                new_r = Self::node(hash, c, &new_r);
+                while f & 1 == 0 && f != 0 {
+                    f >>= 1;
+                    s >>= 1;
+                }
+            } else {
+                new_r = Self::node(hash, &new_r, c);
+            }
+            f >>= 1;
+            s >>= 1;
+        }
+
+        s == 0 && old_r == old_root && new_r == new_root
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cavp::{from_hex, to_hex};
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    // test vectors of the Certificate Transparency reference implementation
+    const LEAVES: [&str; 8] = [
+        "",
+        "00",
+        "10",
+        "2021",
+        "3031",
+        "40414243",
+        "5051525354555657",
+        "606162636465666768696a6b6c6d6e6f",
+    ];
+
+    const ROOTS: [&str; 8] = [
+        "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+        "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+        "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+        "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+        "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+        "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+        "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+        "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+    ];
+
+    fn tree() -> MerkleTree<u32, 64, 64> {
+        let mut tree = MerkleTree::new(Sha256::new());
+        for leaf in &LEAVES {
+            tree.push(&from_hex(leaf).unwrap());
+        }
+        tree
+    }
+
+    fn hex(nodes: &[Vec<u8>]) -> Vec<String> {
+        nodes.iter().map(|n| to_hex(n)).collect()
+    }
+
+    #[test]
+    fn roots() {
+        let tree = tree();
+        assert_eq!(tree.len(), 8);
+        for (size, root) in ROOTS.iter().enumerate() {
+            assert_eq!(to_hex(&tree.root_at(size + 1).unwrap()), *root);
+        }
+        assert_eq!(to_hex(&tree.root()), ROOTS[7]);
+        assert_eq!(tree.root_at(9), None);
+
+        let empty = MerkleTree::new(Sha256::new());
+        assert!(empty.is_empty());
+        assert_eq!(
+            to_hex(&empty.root()),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+    }
+
+    #[test]
+    fn audit_paths() {
+        let tree = tree();
+        let sha256 = Sha256::new();
+        assert_eq!(
+            hex(&tree.audit_path(5).unwrap()),
+            vec![
+                "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]
+        );
+        assert_eq!(tree.audit_path(8), None);
+
+        // every leaf of every tree size
+        for size in 1..=8 {
+            let mut partial = MerkleTree::new(Sha256::new());
+            for leaf in &LEAVES[..size] {
+                partial.push(&from_hex(leaf).unwrap());
+            }
+            let root = partial.root();
+
+            for (index, leaf) in LEAVES[..size].iter().enumerate() {
+                let leaf_hash = partial.leaf_hash(&from_hex(leaf).unwrap());
+                let path = partial.audit_path(index).unwrap();
+                assert!(MerkleTree::verify_inclusion(
+                    &sha256, &leaf_hash, index, size, &path, &root
+                ));
+
+                // wrong index, root or path
+                let mut wrong_root = root.clone();
+                wrong_root[0] ^= 1;
+                assert!(!MerkleTree::verify_inclusion(
+                    &sha256,
+                    &leaf_hash,
+                    index + 1,
+                    size,
+                    &path,
+                    &root
+                ));
+                assert!(!MerkleTree::verify_inclusion(
+                    &sha256,
+                    &leaf_hash,
+                    index,
+                    size,
+                    &path,
+                    &wrong_root
+                ));
+                if !path.is_empty() {
+                    assert!(!MerkleTree::verify_inclusion(
+                        &sha256,
+                        &leaf_hash,
+                        index,
+                        size,
+                        &path[1..],
+                        &root
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn consistency_proofs() {
+        let tree = tree();
+        let sha256 = Sha256::new();
+        assert_eq!(
+            hex(&tree.consistency_proof(6).unwrap()),
+            vec![
+                "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            ]
+        );
+        assert_eq!(tree.consistency_proof(9), None);
+
+        for new_size in 1..=8 {
+            let new_root = tree.root_at(new_size).unwrap();
+            let mut partial = MerkleTree::new(Sha256::new());
+            for leaf in &LEAVES[..new_size] {
+                partial.push(&from_hex(leaf).unwrap());
+            }
+
+            for old_size in 0..=new_size {
+                let old_root = tree.root_at(old_size).unwrap();
+                let proof = partial.consistency_proof(old_size).unwrap();
+                assert!(MerkleTree::verify_consistency(
+                    &sha256, old_size, new_size, &old_root, &new_root, &proof
+                ));
+
+                if old_size > 0 && old_size < new_size {
+                    assert!(!MerkleTree::verify_consistency(
+                        &sha256, old_size, new_size, &new_root, &new_root, &proof
+                    ));
+                    assert!(!MerkleTree::verify_consistency(
+                        &sha256,
+                        old_size,
+                        new_size,
+                        &old_root,
+                        &new_root,
+                        &proof[1..]
+                    ));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sha512_tree() {
+        let mut tree = MerkleTree::new(Sha512::new());
+        tree.push(b"a");
+        tree.push(b"b");
+        tree.push(b"c");
+
+        let root = tree.root();
+        assert_eq!(root.len(), 64);
+        let leaf_hash = tree.leaf_hash(b"c");
+        let path = tree.audit_path(2).unwrap();
+        assert!(MerkleTree::verify_inclusion(
+            &Sha512::new(),
+            &leaf_hash,
+            2,
+            3,
+            &path,
+            &root
+        ));
+    }
+}
@@ -0,0 +1,174 @@
+use core::fmt::LowerHex;
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+
+// MGF1 mask generation function (PKCS#1 v2.2, RFC 8017 B.2.1) for RSA-OAEP and RSA-PSS: the mask is
+// H(seed || counter) for counter = 0, 1, 2... as 32-bit big-endian, truncated to the mask length.
+// The hash given is only used as a template, e.g.: mgf1(&Sha256::new(), seed, &mut mask)
+pub fn mgf1<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+    seed: &[u8],
+    mask: &mut [u8],
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    generate(hash, seed, mask, |m, d| *m = d);
+}
+
+// data is xored with the mask of the seed, as done for maskedDB and maskedSeed of OAEP or
+// maskedDB of PSS
+pub fn mgf1_xor<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+    seed: &[u8],
+    data: &mut [u8],
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    generate(hash, seed, data, |m, d| *m ^= d);
+}
+
+// mask bytes are combined into out by apply, the mask length being limited by the 32-bit counter
+fn generate<T, const BLOCKSIZE: usize, const ROUNDS: usize>(
+    hash: &Hash<T, BLOCKSIZE, ROUNDS>,
+    seed: &[u8],
+    out: &mut [u8],
+    apply: impl Fn(&mut u8, u8),
+) where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    let n = hash.digest_size;
+    assert!(
+        out.len() as u64 <= (n as u64) << 32,
+        "mask too long for a 32-bit counter"
+    );
+
+    let mut digest = [0u8; 64];
+    for (counter, chunk) in out.chunks_mut(n).enumerate() {
+        let mut h = hash.fork();
+        h.update(seed);
+        h.update(&(counter as u32).to_be_bytes());
+        h.finalize();
+        h.digest_into(&mut digest[..n]);
+
+        // the last chunk may be shorter than the digest
+        for (byte, d) in chunk.iter_mut().zip(digest.iter()) {
+            apply(byte, *d);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    fn hex(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn mgf1_sha1() {
+        let mut mask = [0u8; 3];
+        mgf1(&Sha1::new(), b"foo", &mut mask);
+        assert_eq!(hex(&mask), "1ac907");
+
+        let mut mask = [0u8; 5];
+        mgf1(&Sha1::new(), b"foo", &mut mask);
+        assert_eq!(hex(&mask), "1ac9075cd4");
+
+        mgf1(&Sha1::new(), b"bar", &mut mask);
+        assert_eq!(hex(&mask), "bc0c655e01");
+
+        let mut mask = [0u8; 50];
+        mgf1(&Sha1::new(), b"bar", &mut mask);
+        assert_eq!(
+            hex(&mask),
+            "bc0c655e016bc2931d85a2e675181adcef7f581f76df2739da74faac41627be2f7f415c89e983fd0ce80ced9878641cb4876"
+        );
+    }
+
+    #[test]
+    fn mgf1_sha2() {
+        let mut mask = [0u8; 50];
+        mgf1(&Sha256::new(), b"bar", &mut mask);
+        assert_eq!(
+            hex(&mask),
+            "382576a7841021cc28fc4c0948753fb8312090cea942ea4c4e735d10dc724b155f9f6069f289d61daca0cb814502ef04eae1"
+        );
+
+        mgf1(&Sha512::new(), b"bar", &mut mask);
+        assert_eq!(
+            hex(&mask),
+            "8625c97145f50577911b25359975c8f942487e7aa0167e6db44239680d08547afcef6d3b7080cde5c1d9a8b17acfe7d95b9f"
+        );
+
+        // three counter values, the last one truncated
+        let mut mask = [0u8; 129];
+        mgf1(&Sha512::new(), b"foo", &mut mask);
+        assert_eq!(
+            hex(&mask),
+            "e8ac84d032cd89026d0654d269a810e61c81fc8f978e8b22b1556897463fe36c86955dfee5dc87f38c5e0c4b6faae03da5c7b509f98a8f20f69bd69ba0d95f15c047592edef000c8fa59b4746bf3bb4f7b9746a05c37207b702131daaeaa144cf2239a003f442f9fe49fc4f8160453d549b7d1738a835f5829b92c1b1dfe14f460"
+        );
+
+        // the empty mask
+        mgf1(&Sha256::new(), b"bar", &mut []);
+    }
+
+    #[test]
+    fn xor() {
+        let mut mask = [0u8; 100];
+        mgf1(&Sha256::new(), b"seed", &mut mask);
+
+        let mut data = [0x5au8; 100];
+        mgf1_xor(&Sha256::new(), b"seed", &mut data);
+        for (d, m) in data.iter().zip(mask.iter()) {
+            assert_eq!(*d, m ^ 0x5a);
+        }
+
+        // masking twice gives the data back
+        mgf1_xor(&Sha256::new(), b"seed", &mut data);
+        assert_eq!(data, [0x5au8; 100]);
+    }
+
+    // EME-OAEP encoding of the "RSAES-OAEP Encryption Test Vector Int" example of PKCS#1 v2.1
+    // (oaep-int.txt, 1024-bit key, SHA-1, empty label), whose RSA encryption gives the ciphertext
+    // of the example
+    #[test]
+    fn oaep() {
+        let message = [
+            0xd4, 0x36, 0xe9, 0x95, 0x69, 0xfd, 0x32, 0xa7, 0xc8, 0xa0, 0x5b, 0xbc, 0x90, 0xd3,
+            0x2c, 0x49,
+        ];
+        let mut seed = [
+            0xaa, 0xfd, 0x12, 0xf6, 0x59, 0xca, 0xe6, 0x34, 0x89, 0xb4, 0x79, 0xe5, 0x07, 0x6d,
+            0xde, 0xc2, 0xf0, 0x6c, 0xb5, 0x8f,
+        ];
+
+        // DB = lHash || PS || 01 || M
+        let mut label_hash = Sha1::new();
+        label_hash.finalize();
+        let mut db = [0u8; 107];
+        label_hash.digest_into(&mut db[..20]);
+        db[90] = 1;
+        db[91..].copy_from_slice(&message);
+
+        mgf1_xor(&Sha1::new(), &seed, &mut db);
+        mgf1_xor(&Sha1::new(), &db, &mut seed);
+        assert_eq!(
+            format!("00{}{}", hex(&seed), hex(&db)),
+            "00eb7a19ace9e3006350e329504b45e2ca82310b26dcd87d5c68f1eea8f55267c31b2e8bb4251f84d7e0b2c04626f5aff93edcfb25c9c2b3ff8ae10e839a2ddb4cdcfe4ff47728b4a1b7c1362baad29ab48d2869d5024121435811591be392f982fb3e87d095aeb40448db972f3ac14f7bc275195281ce32d2f1b76d4d353e2d"
+        );
+    }
+}
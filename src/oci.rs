@@ -0,0 +1,442 @@
+use std::fmt::Display;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::cavp::{from_hex, to_hex};
+use crate::file::ReadMode;
+use crate::sha256::Sha256;
+use crate::sha512::Sha512;
+
+// OCI content digests (image-spec descriptor.md), e.g.: sha256:<64 lowercase hex digits>. Blobs of
+// an image layout are stored as blobs/<algorithm>/<hex digest>
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OciAlgorithm {
+    Sha256,
+    Sha512,
+}
+
+impl OciAlgorithm {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "sha256" => Some(OciAlgorithm::Sha256),
+            "sha512" => Some(OciAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OciAlgorithm::Sha256 => "sha256",
+            OciAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    pub fn digest_size(&self) -> usize {
+        match self {
+            OciAlgorithm::Sha256 => 32,
+            OciAlgorithm::Sha512 => 64,
+        }
+    }
+}
+
+impl Display for OciAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OciDigest {
+    pub algorithm: OciAlgorithm,
+    pub digest: Vec<u8>,
+}
+
+impl OciDigest {
+    pub fn of(algorithm: OciAlgorithm, data: &[u8]) -> Self {
+        let digest = match algorithm {
+            OciAlgorithm::Sha256 => Sha256::new().digest_of(data),
+            OciAlgorithm::Sha512 => Sha512::new().digest_of(data),
+        };
+        OciDigest { algorithm, digest }
+    }
+
+    pub fn of_file<P: AsRef<Path>>(
+        algorithm: OciAlgorithm,
+        path: P,
+        mode: ReadMode,
+    ) -> std::io::Result<Self> {
+        let digest = match algorithm {
+            OciAlgorithm::Sha256 => Sha256::new().file_digest(path, mode)?,
+            OciAlgorithm::Sha512 => Sha512::new().file_digest(path, mode)?,
+        };
+        Ok(OciDigest { algorithm, digest })
+    }
+
+    // the encoded part, after the algorithm
+    pub fn encoded(&self) -> String {
+        to_hex(&self.digest)
+    }
+
+    pub fn verify(&self, data: &[u8]) -> bool {
+        OciDigest::of(self.algorithm, data) == *self
+    }
+
+    // the blob is hashed with the algorithm of the digest
+    pub fn verify_blob<P: AsRef<Path>>(&self, path: P, mode: ReadMode) -> std::io::Result<bool> {
+        Ok(OciDigest::of_file(self.algorithm, path, mode)? == *self)
+    }
+
+    // path of the blob in an image layout
+    pub fn blob_path<P: AsRef<Path>>(&self, layout: P) -> std::path::PathBuf {
+        layout
+            .as_ref()
+            .join("blobs")
+            .join(self.algorithm.name())
+            .join(self.encoded())
+    }
+}
+
+impl Display for OciDigest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.algorithm, self.encoded())
+    }
+}
+
+fn invalid_digest(digest: &str, msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidInput,
+        format!("invalid digest {}: {}", digest, msg),
+    )
+}
+
+// registered algorithms only: sha256 and sha512 encode their digest as lowercase hex
+impl FromStr for OciDigest {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (name, encoded) = s
+            .split_once(':')
+            .ok_or_else(|| invalid_digest(s, "missing algorithm"))?;
+        let algorithm = OciAlgorithm::from_name(name)
+            .ok_or_else(|| invalid_digest(s, "unsupported algorithm"))?;
+
+        if encoded.len() != 2 * algorithm.digest_size()
+            || !encoded
+                .bytes()
+                .all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'))
+        {
+            return Err(invalid_digest(
+                s,
+                &format!(
+                    "{} digests are {} lowercase hex digits",
+                    algorithm,
+                    2 * algorithm.digest_size()
+                ),
+            ));
+        }
+
+        Ok(OciDigest {
+            algorithm,
+            digest: from_hex(encoded).ok_or_else(|| invalid_digest(s, "invalid hex"))?,
+        })
+    }
+}
+
+// outcome of an image layout validation
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LayoutReport {
+    pub verified: usize,
+    pub failed: Vec<String>, // blobs not matching their name, e.g.: "blobs/sha256/abc..."
+    pub missing: Vec<String>, // digests referenced from index.json without a blob
+    pub skipped: Vec<String>, // unsupported algorithm directories or digests
+}
+
+impl LayoutReport {
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty() && self.missing.is_empty()
+    }
+}
+
+impl Display for LayoutReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} blobs verified, {} failed, {} missing, {} skipped",
+            self.verified,
+            self.failed.len(),
+            self.missing.len(),
+            self.skipped.len()
+        )
+    }
+}
+
+#[cfg(feature = "oci")]
+fn invalid_layout(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid OCI image layout: {}", msg),
+    )
+}
+
+// every blob must hash to its file name, and the blobs referenced from index.json must exist:
+// manifests and nested indexes, then the config and layers of each manifest
+#[cfg(feature = "oci")]
+pub fn validate_layout<P: AsRef<Path>>(dir: P, mode: ReadMode) -> std::io::Result<LayoutReport> {
+    use serde_json::Value;
+    use std::collections::HashSet;
+
+    let dir = dir.as_ref();
+    if !dir.join("oci-layout").is_file() {
+        return Err(invalid_layout("no oci-layout file"));
+    }
+    let index = std::fs::read_to_string(dir.join("index.json"))
+        .map_err(|e| invalid_layout(&format!("unable to read index.json: {}", e)))?;
+    let index: Value =
+        serde_json::from_str(&index).map_err(|e| invalid_layout(&format!("index.json: {}", e)))?;
+
+    let mut report = LayoutReport::default();
+
+    // sorted for a stable report
+    let mut algorithms: Vec<_> = std::fs::read_dir(dir.join("blobs"))?
+        .collect::<Result<Vec<_>, _>>()?
+        .into_iter()
+        .map(|entry| entry.path())
+        .collect();
+    algorithms.sort();
+
+    for algorithm_dir in algorithms {
+        let name = algorithm_dir
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let blob_name = |file: &Path| {
+            format!(
+                "blobs/{}/{}",
+                name,
+                file.file_name().unwrap_or_default().to_string_lossy()
+            )
+        };
+
+        if OciAlgorithm::from_name(&name).is_none() || !algorithm_dir.is_dir() {
+            report.skipped.push(format!("blobs/{}", name));
+            continue;
+        }
+
+        let mut blobs: Vec<_> = std::fs::read_dir(&algorithm_dir)?
+            .collect::<Result<Vec<_>, _>>()?
+            .into_iter()
+            .map(|entry| entry.path())
+            .collect();
+        blobs.sort();
+
+        for blob in blobs {
+            let expected = format!(
+                "{}:{}",
+                name,
+                blob.file_name().unwrap_or_default().to_string_lossy()
+            );
+            let valid = match expected.parse::<OciDigest>() {
+                Ok(digest) => digest.verify_blob(&blob, mode)?,
+                Err(_) => false,
+            };
+
+            if valid {
+                report.verified += 1;
+            } else {
+                report.failed.push(blob_name(&blob));
+            }
+        }
+    }
+
+    // descriptors of a document: "manifests" of an index, "config" and "layers" of a manifest
+    let descriptors = |document: &Value| -> Vec<Value> {
+        let mut descriptors = Vec::new();
+        for key in ["manifests", "layers"] {
+            if let Some(list) = document[key].as_array() {
+                descriptors.extend(list.iter().cloned());
+            }
+        }
+        if document["config"].is_object() {
+            descriptors.push(document["config"].clone());
+        }
+        descriptors
+    };
+
+    // the same blob can be referenced several times, e.g. a layer shared by two manifests
+    let mut visited = HashSet::new();
+    let mut pending = descriptors(&index);
+
+    while let Some(descriptor) = pending.pop() {
+        let digest = descriptor["digest"]
+            .as_str()
+            .ok_or_else(|| invalid_layout(&format!("descriptor without digest: {}", descriptor)))?;
+        if !visited.insert(digest.to_string()) {
+            continue;
+        }
+
+        let digest = match digest.parse::<OciDigest>() {
+            Ok(digest) => digest,
+            Err(e) => match digest.split_once(':') {
+                Some((name, _)) if OciAlgorithm::from_name(name).is_none() => {
+                    report.skipped.push(digest.to_string());
+                    continue;
+                }
+                _ => return Err(invalid_layout(&e.to_string())),
+            },
+        };
+
+        let path = digest.blob_path(dir);
+        if !path.is_file() {
+            report.missing.push(digest.to_string());
+            continue;
+        }
+
+        // only manifests and indexes are walked, and a corrupted one is already reported
+        let media_type = descriptor["mediaType"].as_str().unwrap_or_default();
+        let document = media_type.contains("manifest") || media_type.contains("index");
+        if document && digest.verify_blob(&path, mode)? {
+            let content = std::fs::read(&path)?;
+            let document: Value = serde_json::from_slice(&content)
+                .map_err(|e| invalid_layout(&format!("{}: {}", digest, e)))?;
+            pending.extend(descriptors(&document));
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(not(feature = "oci"))]
+pub fn validate_layout<P: AsRef<Path>>(_dir: P, _mode: ReadMode) -> std::io::Result<LayoutReport> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "image layouts need the oci feature",
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMPTY_SHA256: &str =
+        "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+
+    #[test]
+    fn parse_and_format() {
+        let digest: OciDigest = EMPTY_SHA256.parse().unwrap();
+        assert_eq!(digest.algorithm, OciAlgorithm::Sha256);
+        assert_eq!(digest.to_string(), EMPTY_SHA256);
+        assert_eq!(digest, OciDigest::of(OciAlgorithm::Sha256, b""));
+        assert!(digest.verify(b""));
+        assert!(!digest.verify(b"a"));
+
+        let digest = OciDigest::of(OciAlgorithm::Sha512, b"abc");
+        assert_eq!(
+            digest.to_string(),
+            "sha512:ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f"
+        );
+        assert_eq!(digest.to_string().parse::<OciDigest>().unwrap(), digest);
+    }
+
+    #[test]
+    fn invalid_digests() {
+        for digest in [
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "md5:d41d8cd98f00b204e9800998ecf8427e",
+            "sha256:E3B0C44298FC1C149AFBF4C8996FB92427AE41E4649B934CA495991B7852B855",
+            "sha256:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b8",
+            "sha512:e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "sha256:",
+        ] {
+            assert!(digest.parse::<OciDigest>().is_err(), "{}", digest);
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "oci")]
+    fn image_layout() {
+        let dir = std::env::temp_dir().join(format!("sha_oci_layout_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("blobs/sha256")).unwrap();
+        std::fs::create_dir_all(dir.join("blobs/md5")).unwrap();
+        std::fs::write(dir.join("oci-layout"), r#"{"imageLayoutVersion":"1.0.0"}"#).unwrap();
+
+        let blob = |content: &[u8]| {
+            let digest = OciDigest::of(OciAlgorithm::Sha256, content);
+            std::fs::write(digest.blob_path(&dir), content).unwrap();
+            digest
+        };
+        let descriptor = |media_type: &str, digest: &OciDigest| {
+            format!(r#"{{"mediaType":"{}","digest":"{}"}}"#, media_type, digest)
+        };
+
+        // index.json -> nested index -> manifest -> config and two layers, one of them missing
+        let config = blob(b"{}");
+        let layer = blob(b"layer");
+        let missing = OciDigest::of(OciAlgorithm::Sha256, b"missing layer");
+        let manifest = blob(
+            format!(
+                r#"{{"schemaVersion":2,"config":{},"layers":[{},{}]}}"#,
+                descriptor("application/vnd.oci.image.config.v1+json", &config),
+                descriptor("application/vnd.oci.image.layer.v1.tar", &layer),
+                descriptor("application/vnd.oci.image.layer.v1.tar", &missing)
+            )
+            .as_bytes(),
+        );
+        let nested = blob(
+            format!(
+                r#"{{"schemaVersion":2,"manifests":[{}]}}"#,
+                descriptor("application/vnd.oci.image.manifest.v1+json", &manifest)
+            )
+            .as_bytes(),
+        );
+        std::fs::write(
+            dir.join("index.json"),
+            format!(
+                r#"{{"schemaVersion":2,"manifests":[{}]}}"#,
+                descriptor("application/vnd.oci.image.index.v1+json", &nested)
+            ),
+        )
+        .unwrap();
+
+        let report = validate_layout(&dir, ReadMode::Buffered).unwrap();
+        assert_eq!(report.verified, 4);
+        assert_eq!(report.missing, vec![missing.to_string()]);
+        assert_eq!(report.skipped, vec!["blobs/md5".to_string()]);
+        assert!(!report.is_success());
+
+        // a corrupted blob
+        std::fs::write(missing.blob_path(&dir), b"tampered").unwrap();
+        let report = validate_layout(&dir, ReadMode::Mmap).unwrap();
+        assert_eq!(report.verified, 4);
+        assert_eq!(
+            report.failed,
+            vec![format!("blobs/sha256/{}", missing.encoded())]
+        );
+        assert!(report.missing.is_empty());
+
+        std::fs::write(missing.blob_path(&dir), b"missing layer").unwrap();
+        let report = validate_layout(&dir, ReadMode::Buffered).unwrap();
+        assert!(report.is_success());
+        assert_eq!(
+            report.to_string(),
+            "5 blobs verified, 0 failed, 0 missing, 1 skipped"
+        );
+
+        // a corrupted manifest isn't walked
+        std::fs::write(manifest.blob_path(&dir), b"not json").unwrap();
+        let report = validate_layout(&dir, ReadMode::Buffered).unwrap();
+        assert_eq!(report.verified, 4);
+        assert_eq!(report.failed.len(), 1);
+
+        std::fs::write(dir.join("index.json"), b"{").unwrap();
+        assert!(validate_layout(&dir, ReadMode::Buffered).is_err());
+
+        std::fs::remove_file(dir.join("oci-layout")).unwrap();
+        assert!(validate_layout(&dir, ReadMode::Buffered).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
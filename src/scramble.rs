@@ -1,4 +1,4 @@
-use std::ops::{BitAnd, BitXor, Not, Shr};
+use core::ops::{BitAnd, BitXor, Not, Shr};
 
 // functions used to rotate, shift etc
 pub type FnScramble3<T> = fn(T, T, T) -> T;
@@ -29,7 +29,7 @@ impl Shifter<u64> for u64 {
 }
 
 pub struct Scramble<T> {
-    _id: std::marker::PhantomData<T>,
+    _id: core::marker::PhantomData<T>,
 }
 
 // generic scrambling functions used in SHA calculations. Operate on u32 or u64
@@ -81,9 +81,20 @@ impl<T> Scramble<T> {
     {
         x.right_rotate(A) ^ x.right_rotate(B) ^ x.right_shift(C)
     }
+
+    // Parity(X,Y,Z) = X ⊕ Y ⊕ Z, used by SHA-1
+    #[allow(non_snake_case)]
+    pub fn Parity(x: T, y: T, z: T) -> T
+    where
+        T: BitXor<Output = T>,
+        T: Copy,
+    {
+        x ^ y ^ z
+    }
 }
 
 #[allow(non_snake_case)]
+#[derive(Clone)]
 pub struct ScramblePool<T> {
     pub ch: FnScramble3<T>,
     pub maj: FnScramble3<T>,
@@ -200,6 +211,11 @@ mod tests {
         );
     }
 
+    #[test]
+    fn Parity() {
+        assert_eq!(Scramble::<u32>::Parity(0xAAAA, 0xBBBB, 0xCCCC), 0xDDDD);
+    }
+
     #[test]
     fn σ1() {
         let x: u32 = 0b1111111111111111;
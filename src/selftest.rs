@@ -0,0 +1,371 @@
+use core::fmt::Display;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::hmac::Hmac;
+use crate::sha::Digest;
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+use crate::sha3::Sha3;
+use crate::sha512::Sha512;
+use crate::sp800_185::{cshake128, cshake256, Kmac, ParallelHash, TupleHash};
+
+// power-on self test: known-answer tests of every algorithm, run once on first use. Hashes
+// obtained through checked() are only given if all the tests passed
+
+// 0: not run yet, 1: passed, otherwise 2 + index of the first failed test
+static STATUS: AtomicUsize = AtomicUsize::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SelfTestError {
+    pub algorithm: &'static str,
+}
+
+impl Display for SelfTestError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "{} known-answer test failed", self.algorithm)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SelfTestError {}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KnownAnswerTest {
+    pub algorithm: &'static str,
+    pub passed: bool,
+}
+
+const ABC: &[u8] = b"abc";
+
+// data and key of the NIST SP 800-185 samples
+const SAMPLE_DATA: &[u8] = &[0x00, 0x01, 0x02, 0x03];
+const SAMPLE_KEY: &[u8] = &[
+    0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e, 0x4f,
+    0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d, 0x5e, 0x5f,
+];
+
+// the digest of msg must be the expected hex value
+fn check<D: Digest>(mut digest: D, msg: &[u8], expected: &str) -> bool {
+    digest.update(msg);
+    digest.finalize();
+
+    let mut md = [0u8; 64];
+    let md = &mut md[..expected.len() / 2];
+    digest.digest_into(md);
+    matches(md, expected)
+}
+
+fn matches(md: &[u8], expected: &str) -> bool {
+    md.len() == expected.len() / 2
+        && md
+            .iter()
+            .zip(expected.as_bytes().chunks(2))
+            .all(|(byte, hex)| {
+                core::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|h| u8::from_str_radix(h, 16).ok())
+                    == Some(*byte)
+            })
+}
+
+// algorithm name and its test
+type KnownAnswer = (&'static str, fn() -> bool);
+
+const KNOWN_ANSWER_TESTS: [KnownAnswer; 24] = [
+    ("SHA-1", || {
+        check(Sha1::new(), ABC, "a9993e364706816aba3e25717850c26c9cd0d89d")
+    }),
+    ("SHA-1 with collision detection", || {
+        check(
+            Sha1::new().with_collision_detection(),
+            ABC,
+            "a9993e364706816aba3e25717850c26c9cd0d89d",
+        )
+    }),
+    ("SHA-224", || {
+        check(
+            Sha256::sha224(),
+            ABC,
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7",
+        )
+    }),
+    ("SHA-256", || {
+        check(
+            Sha256::new(),
+            ABC,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+        )
+    }),
+    ("SHA-384", || {
+        check(
+            Sha512::sha384(),
+            ABC,
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7",
+        )
+    }),
+    ("SHA-512", || {
+        check(
+            Sha512::new(),
+            ABC,
+            "ddaf35a193617abacc417349ae20413112e6fa4e89a97ea20a9eeee64b55d39a2192992a274fc1a836ba3c23a3feebbd454d4423643ce80e2a9ac94fa54ca49f",
+        )
+    }),
+    ("SHA3-224", || {
+        check(
+            Sha3::sha3_224(),
+            ABC,
+            "e642824c3f8cf24ad09234ee7d3c766fc9a3a5168d0c94ad73b46fdf",
+        )
+    }),
+    ("SHA3-256", || {
+        check(
+            Sha3::sha3_256(),
+            ABC,
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532",
+        )
+    }),
+    ("SHA3-384", || {
+        check(
+            Sha3::sha3_384(),
+            ABC,
+            "ec01498288516fc926459f58e2c6ad8df9b473cb0fc08c2596da7cf0e49be4b298d88cea927ac7f539f1edf228376d25",
+        )
+    }),
+    ("SHA3-512", || {
+        check(
+            Sha3::sha3_512(),
+            ABC,
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0",
+        )
+    }),
+    ("SHAKE128", || {
+        check(
+            Sha3::shake128(32),
+            ABC,
+            "5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8",
+        )
+    }),
+    ("SHAKE256", || {
+        check(
+            Sha3::shake256(64),
+            ABC,
+            "483366601360a8771c6863080cc4114d8db44530f8f1e1ee4f94ea37e78b5739d5a15bef186a5386c75744c0527e1faa9f8726e462a12a4feb06bd8801e751e4",
+        )
+    }),
+    // output read in pieces across the end of the first block
+    ("SHAKE128 variable-length output", || {
+        let mut shake = Sha3::shake128(0);
+        shake.update(ABC);
+        shake.finalize();
+
+        let mut md = [0u8; 160];
+        shake.squeeze(&mut md);
+        shake.squeeze(&mut md[..7]);
+        shake.squeeze(&mut md[7..32]);
+        matches(
+            &md[..32],
+            "cc29082f5647584e6aa01b3f5af057805f973ff8ecb8b226ac32ada6f01c1fcd",
+        )
+    }),
+    // SP 800-185 samples #1 and #3 of cSHAKE, #1 and #4 of KMAC, #1 of TupleHash and ParallelHash
+    ("cSHAKE128", || {
+        check(
+            cshake128(b"", b"Email Signature", 32),
+            SAMPLE_DATA,
+            "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5",
+        )
+    }),
+    ("cSHAKE256", || {
+        check(
+            cshake256(b"", b"Email Signature", 64),
+            SAMPLE_DATA,
+            "d008828e2b80ac9d2218ffee1d070c48b8e4c87bff32c9699d5b6896eee0edd164020e2be0560858d9c00c037e34a96937c561a74c412bb4c746469527281c8c",
+        )
+    }),
+    ("KMAC128", || {
+        check(
+            Kmac::kmac128(SAMPLE_KEY, b"", 32),
+            SAMPLE_DATA,
+            "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e",
+        )
+    }),
+    ("KMAC256", || {
+        check(
+            Kmac::kmac256(SAMPLE_KEY, b"My Tagged Application", 64),
+            SAMPLE_DATA,
+            "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd",
+        )
+    }),
+    ("TupleHash128", || {
+        let mut tuple_hash = TupleHash::tuple_hash128(b"", 32);
+        tuple_hash.push(&[0x00, 0x01, 0x02]);
+        tuple_hash.push(&[0x10, 0x11, 0x12, 0x13, 0x14, 0x15]);
+        tuple_hash.finalize();
+        matches(
+            &tuple_hash.digest::<32>(),
+            "c5d8786c1afb9b82111ab34b65b2c0048fa64e6d48e263264ce1707d3ffc8ed1",
+        )
+    }),
+    ("ParallelHash128", || {
+        check(
+            ParallelHash::parallel_hash128(8, b"", 32),
+            &[
+                0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15,
+                0x16, 0x17, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27,
+            ],
+            "ba8dc1d1d979331d3f813603c67f72609ab5e44b94a0b8f9af46514454a2b4f5",
+        )
+    }),
+    // RFC 2202 and RFC 4231 test case 2
+    ("HMAC-SHA-1", || {
+        check(
+            Hmac::new(Sha1::new(), b"Jefe"),
+            b"what do ya want for nothing?",
+            "effcdf6ae5eb2fa2d27416d5f184df9c259a7c79",
+        )
+    }),
+    ("HMAC-SHA-224", || {
+        check(
+            Hmac::new(Sha256::sha224(), b"Jefe"),
+            b"what do ya want for nothing?",
+            "a30e01098bc6dbbf45690f3a7e9e6d0f8bbea2a39e6148008fd05e44",
+        )
+    }),
+    ("HMAC-SHA-256", || {
+        check(
+            Hmac::new(Sha256::new(), b"Jefe"),
+            b"what do ya want for nothing?",
+            "5bdcc146bf60754e6a042426089575c75a003f089d2739839dec58b964ec3843",
+        )
+    }),
+    ("HMAC-SHA-384", || {
+        check(
+            Hmac::new(Sha512::sha384(), b"Jefe"),
+            b"what do ya want for nothing?",
+            "af45d2e376484031617f78d2b58a6b1b9c7ef464f5a01b47e42ec3736322445e8e2240ca5e69e2c78b3239ecfab21649",
+        )
+    }),
+    ("HMAC-SHA-512", || {
+        check(
+            Hmac::new(Sha512::new(), b"Jefe"),
+            b"what do ya want for nothing?",
+            "164b7a7bfcf819e2e395fbe73b56e0a387bd64222e831fd610270cd7ea2505549758bf75c05a994a6d034f65f8f0e6fdcaeab1a34d4a6b4b636e070a38bce737",
+        )
+    }),
+];
+
+// run every known-answer test, without caching the result
+pub fn known_answer_tests() -> impl Iterator<Item = KnownAnswerTest> {
+    KNOWN_ANSWER_TESTS
+        .iter()
+        .map(|(algorithm, test)| KnownAnswerTest {
+            algorithm,
+            passed: test(),
+        })
+}
+
+// the tests are run on the first call only
+pub fn self_test() -> Result<(), SelfTestError> {
+    run(&STATUS, &KNOWN_ANSWER_TESTS)
+}
+
+// give back the hash only if the self test passed, e.g.: let sha256 = checked(Sha256::new())?;
+pub fn checked<D: Digest>(digest: D) -> Result<D, SelfTestError> {
+    checked_with(&STATUS, &KNOWN_ANSWER_TESTS, digest)
+}
+
+fn checked_with<D: Digest>(
+    status: &AtomicUsize,
+    tests: &[KnownAnswer],
+    digest: D,
+) -> Result<D, SelfTestError> {
+    run(status, tests)?;
+    Ok(digest)
+}
+
+// run the tests if the status is 0, otherwise give back the result cached in status
+fn run(status: &AtomicUsize, tests: &[KnownAnswer]) -> Result<(), SelfTestError> {
+    let mut result = status.load(Ordering::Acquire);
+
+    if result == 0 {
+        result = match tests.iter().position(|(_, test)| !test()) {
+            Some(failed) => 2 + failed,
+            None => 1,
+        };
+        status.store(result, Ordering::Release);
+    }
+
+    match result {
+        1 => Ok(()),
+        failed => Err(SelfTestError {
+            algorithm: tests[failed - 2].0,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_passed() {
+        assert!(known_answer_tests().all(|kat| kat.passed));
+        assert_eq!(known_answer_tests().count(), KNOWN_ANSWER_TESTS.len());
+
+        assert!(self_test().is_ok());
+        assert_eq!(STATUS.load(Ordering::Acquire), 1);
+
+        let mut sha256 = checked(Sha256::new()).unwrap();
+        sha256.update(ABC);
+        sha256.finalize();
+        assert_eq!(
+            sha256.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn failed_test() {
+        static STATUS: AtomicUsize = AtomicUsize::new(0);
+        static RUNS: AtomicUsize = AtomicUsize::new(0);
+
+        let tests: [KnownAnswer; 3] = [
+            KNOWN_ANSWER_TESTS[0],
+            ("SHA-256", || {
+                RUNS.fetch_add(1, Ordering::Relaxed);
+                check(
+                    Sha256::new(),
+                    b"abd",
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad",
+                )
+            }),
+            ("never run", || unreachable!()),
+        ];
+        let failed = Err(SelfTestError {
+            algorithm: "SHA-256",
+        });
+        assert_eq!(run(&STATUS, &tests), failed);
+        assert_eq!(STATUS.load(Ordering::Acquire), 3);
+
+        // the failure is cached: the tests aren't run again, and no hash is given
+        assert_eq!(run(&STATUS, &tests), failed);
+        assert_eq!(RUNS.load(Ordering::Relaxed), 1);
+        assert!(checked_with(&STATUS, &tests, Sha256::new()).is_err());
+    }
+
+    #[test]
+    fn wrong_answer() {
+        assert!(!check(
+            Sha256::new(),
+            b"abd",
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        ));
+        assert_eq!(
+            SelfTestError {
+                algorithm: "SHA-256"
+            }
+            .to_string(),
+            "SHA-256 known-answer test failed"
+        );
+    }
+}
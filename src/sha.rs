@@ -1,22 +1,58 @@
-#[allow(non_snake_case)]
-use std::default::Default;
-use std::fmt::{Display, LowerHex};
+use core::default::Default;
+use core::fmt::{Display, LowerHex};
+#[cfg(feature = "std")]
 use std::io::BufRead;
 
 use crate::convert::Modular;
 use crate::scramble::ScramblePool;
+use crate::trace::{NoTrace, Observer};
+
+// size of the buffer used by message_hash to read from the reader
+pub const DEFAULT_BUFFER_SIZE: usize = 64 * 1024;
+
+// compression function, processing a BLOCKSIZE block into the hash value: SHA-2 or SHA-1
+pub type FnCompress<T, const BLOCKSIZE: usize, const ROUNDS: usize> =
+    fn(&mut Hash<T, BLOCKSIZE, ROUNDS>, &[u8]);
 
-// hash is either 256 or 512 bits but always 8 u32 or u64 integers
-// T is either u32 or u64
+// hash is always 8 u32 or u64 integers, but the final value can be truncated to digest_size
+// (e.g. SHA-1 only uses 5 words). T is either u32 or u64
+#[derive(Clone)]
 pub struct Hash<T, const BLOCKSIZE: usize, const ROUNDS: usize> {
-    pub k_constants: [T; ROUNDS],   // ROUNDS = 64 or 80
+    pub k_constants: [T; ROUNDS], // ROUNDS = 64 or 80
     pub hash: [T; 8],
     pub scramble_funcs: ScramblePool<T>, // scrambling functions σ etc
-    pub block: [u8; BLOCKSIZE], // BLOCKSIZE = 64 or 128
+    pub block: [u8; BLOCKSIZE],          // BLOCKSIZE = 64 or 128
+    pub block_len: usize,                // number of bytes waiting in block
+    pub length: u64,                     // number of message bytes processed so far
+    pub buffer_size: usize,              // size of the reads in message_hash
+    pub compress_func: FnCompress<T, BLOCKSIZE, ROUNDS>,
+    pub digest_size: usize,       // in bytes, 32 or 64 for SHA-2
+    pub state_words: usize,       // words of hash used by compress_func: 5 for SHA-1, 8 for SHA-2
+    pub collision_detected: bool, // only set by SHA-1 with collision detection
+    pub rounds: usize,            // SHA-2 rounds actually computed, at most ROUNDS
+    pub standard: bool,           // false for the experimental variants of Sha2Builder
 }
 
 #[allow(non_snake_case)]
 impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS> {
+    // set the size of the buffer used to read, each read hashing many blocks at once
+    pub fn with_buffer_size(mut self, buffer_size: usize) -> Self {
+        assert!(buffer_size > 0, "buffer size can't be 0");
+        self.buffer_size = buffer_size;
+        self
+    }
+
+    // snapshot of a running hash: finalizing the fork gives the hash of the data so far while
+    // the original can continue with more data
+    pub fn fork(&self) -> Self
+    where
+        T: Clone,
+    {
+        self.clone()
+    }
+
+    // hash the whole content of a reader, which should return message_length bytes
+    #[cfg(feature = "std")]
     pub fn message_hash<R: BufRead>(
         &mut self,
         message_length: u64,
@@ -28,54 +64,150 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
         T: Modular<T>,
         T: LowerHex,
     {
-        let mut last_bytes_read = 0usize;
+        let mut buffer = vec![0u8; self.buffer_size];
+        let mut total_bytes_read = 0u64;
 
         loop {
-            match reader.read(&mut self.block) {
+            match reader.read(&mut buffer) {
+                // EOF
+                Ok(0) => break,
                 Ok(bytes_read) => {
-                    // EOF: save last file address to restart from this address for next run
-                    if bytes_read == 0 {
-                        // the message is a multiple of BLOCKSIZE. It still need to be padded
-                        if last_bytes_read == BLOCKSIZE {
-                            let _ = self.block_padding(0, message_length);
-                            self.block_hash();
-                        }
-
-                        break;
-                    }
-
-                    last_bytes_read = bytes_read;
-
-                    // if bytes_read is exactly BLOCKSIZE, still need to hash
-                    if bytes_read == BLOCKSIZE {
-                        self.block_hash();
-                    // padd buffer if block size is < BLOCKSIZE
-                    } else if bytes_read < BLOCKSIZE {
-                        // padd buffer if block size is < BLOCKSIZE
-                        let additional_block = self.block_padding(bytes_read, message_length);
-
-                        // anyway, hash the last or before last block
-                        self.block_hash();
-
-                        // if an additional block is created, use it
-                        if let Some(new_block) = additional_block {
-                            self.block = new_block;
-                            self.block_hash();
-                        }
-                    } else {
-                        panic!("bytes_read > BLOCKSIZE which shouldn't occur");
-                    }
+                    total_bytes_read += bytes_read as u64;
+                    self.update(&buffer[..bytes_read]);
                 }
+                Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
                 Err(err) => {
                     return Err(err);
                 }
             };
         }
 
+        if total_bytes_read != message_length {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "expected {} bytes but {} were read",
+                    message_length, total_bytes_read
+                ),
+            ));
+        }
+
+        self.finalize();
         Ok(())
     }
 
-    fn message_schedule(&self) -> [T; ROUNDS]
+    // add data to the message: full blocks are hashed, remaining bytes are kept in block
+    pub fn update(&mut self, mut data: &[u8])
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        self.length += data.len() as u64;
+
+        // first complete the bytes left from a previous call
+        if self.block_len > 0 {
+            let n = core::cmp::min(BLOCKSIZE - self.block_len, data.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&data[..n]);
+            self.block_len += n;
+            data = &data[n..];
+
+            if self.block_len < BLOCKSIZE {
+                return;
+            }
+            self.block_hash();
+            self.block_len = 0;
+        }
+
+        // whole blocks are hashed straight from data
+        let mut blocks = data.chunks_exact(BLOCKSIZE);
+        for block in &mut blocks {
+            self.compress(block);
+        }
+
+        // keep the remaining bytes for the next call
+        let remainder = blocks.remainder();
+        self.block[..remainder.len()].copy_from_slice(remainder);
+        self.block_len = remainder.len();
+    }
+
+    // pad and hash the remaining bytes. hash then contains the final hash value
+    pub fn finalize(&mut self)
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        let additional_block = self.block_padding(self.block_len, self.length);
+
+        // anyway, hash the last or before last block
+        self.block_hash();
+
+        // if an additional block is created, use it
+        if let Some(new_block) = additional_block {
+            self.block = new_block;
+            self.block_hash();
+        }
+
+        self.block_len = 0;
+    }
+
+    // copy the hash value as big endian bytes, truncated to the out length (at most digest_size)
+    pub fn digest_into(&self, out: &mut [u8])
+    where
+        T: Modular<T>,
+    {
+        // the words after a truncated digest, e.g. the 8th one of SHA-224, must not leak
+        assert!(
+            out.len() <= self.digest_size,
+            "digest longer than {} bytes",
+            self.digest_size
+        );
+
+        let size = core::mem::size_of::<T>();
+        for (word, chunk) in self.hash.iter().zip(out.chunks_mut(size)) {
+            word.to_bytes(chunk);
+        }
+    }
+
+    // hash value as a fixed array, e.g.: let digest: [u8; 32] = sha256.digest();
+    pub fn digest<const N: usize>(&self) -> [u8; N]
+    where
+        T: Modular<T>,
+    {
+        let mut digest = [0u8; N];
+        self.digest_into(&mut digest);
+        digest
+    }
+
+    // hash value as digest_size bytes, when the size is only known at run time
+    #[cfg(feature = "std")]
+    pub fn digest_vec(&self) -> Vec<u8>
+    where
+        T: Modular<T>,
+    {
+        let mut digest = vec![0u8; self.digest_size];
+        self.digest_into(&mut digest);
+        digest
+    }
+
+    // digest of a whole message, e.g.: Sha512::sha384().digest_of(b"abc")
+    #[cfg(feature = "std")]
+    pub fn digest_of(mut self, data: &[u8]) -> Vec<u8>
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        self.update(data);
+        self.finalize();
+        self.digest_vec()
+    }
+
+    fn message_schedule(&self, block: &[u8]) -> [T; ROUNDS]
     where
         T: Default,
         T: Copy,
@@ -86,14 +218,11 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
         let mut w = [T::default(); ROUNDS];
 
         // get the u32 or u64 integer bit size to split the block into chunks
-        let size = std::mem::size_of::<T>();
+        let size = core::mem::size_of::<T>();
 
-        // get a 'window' of 4 or 8 bytes each
-        let mut iter = self.block.chunks(size);
-
-        // first 16 words are the same
-        for i in 0..16 {
-            w[i] = T::to_uint(iter.next().unwrap());
+        // first 16 words are the same, taken from a 'window' of 4 or 8 bytes each
+        for (word, chunk) in w.iter_mut().zip(block.chunks(size)) {
+            *word = T::to_uint(chunk);
         }
 
         // remaining words are given by a formula
@@ -104,14 +233,41 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
             w[i] = s2.add_modulo(w[i - 16]);
         }
 
-        // for i in 0..N {
-        //     print!("w[{}]={:0x} ", i, w[i]);
-        // }
         w
     }
 
     // a round of sha256 calculation
     pub fn block_hash(&mut self)
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        let block = self.block;
+        self.compress(&block);
+    }
+
+    // same as block_hash() but for a BLOCKSIZE slice, to avoid copying it into block
+    fn compress(&mut self, block: &[u8]) {
+        debug_assert_eq!(block.len(), BLOCKSIZE);
+        (self.compress_func)(self, block);
+    }
+
+    // SHA-2 compression function
+    pub fn sha2_compress(&mut self, block: &[u8])
+    where
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        self.sha2_rounds(block, 0, &mut NoTrace);
+    }
+
+    // SHA-2 compression function, the observer being given the intermediate values of the
+    // block_index-th block. NoTrace compiles to sha2_compress without any overhead
+    pub fn sha2_rounds<O: Observer<T>>(&mut self, block: &[u8], block_index: u64, observer: &mut O)
     where
         T: Default,
         T: Copy,
@@ -119,7 +275,8 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
         T: LowerHex,
     {
         // decompose block
-        let W = self.message_schedule();
+        let W = self.message_schedule(block);
+        observer.block(block_index, &W);
 
         // build tmp variables
         let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h) = (
@@ -133,12 +290,18 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
             self.hash[7],
         );
 
-        // 64 rounds
-        for i in 0..ROUNDS {
+        // 64 or 80 rounds, unless reduced
+        for (t, (k, w)) in self
+            .k_constants
+            .iter()
+            .zip(W.iter())
+            .take(self.rounds)
+            .enumerate()
+        {
             let s1 = h.add_modulo((self.scramble_funcs.Σ1)(e));
             let s2 = s1.add_modulo((self.scramble_funcs.ch)(e, f, g));
-            let s3 = s2.add_modulo(self.k_constants[i]);
-            let T1 = s3.add_modulo(W[i]);
+            let s3 = s2.add_modulo(*k);
+            let T1 = s3.add_modulo(*w);
             //let T1 = h + Sigma1(e) + Ch(e, f, g) + K[i] + W[i];
 
             let T2 = (self.scramble_funcs.Σ0)(a).add_modulo((self.scramble_funcs.maj)(a, b, c));
@@ -151,10 +314,7 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
             c = b;
             b = a;
             a = T1.add_modulo(T2);
-            // println!(
-            //     "{:08x} {:08x} {:08x} {:08x} {:08x} {:08x} {:08x} {:08x} ",
-            //     a, b, c, d, e, f, g, h
-            // );
+            observer.round(t, &[a, b, c, d, e, f, g, h]);
         }
 
         // reallocate H
@@ -166,6 +326,7 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
         self.hash[5] = self.hash[5].add_modulo(f);
         self.hash[6] = self.hash[6].add_modulo(g);
         self.hash[7] = self.hash[7].add_modulo(h);
+        observer.hash(&self.hash);
     }
 
     // pad block
@@ -213,6 +374,52 @@ impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
         T: Copy,
     {
         self.hash = [T::default(); 8];
+        self.block_len = 0;
+        self.length = 0;
+        self.collision_detected = false;
+    }
+}
+
+// common interface of the hash algorithms, used by the adaptors and constructions built on top of them
+pub trait Digest {
+    fn update(&mut self, data: &[u8]);
+    fn finalize(&mut self);
+    fn digest_into(&self, out: &mut [u8]);
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Digest for Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    fn update(&mut self, data: &[u8]) {
+        Hash::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        Hash::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        Hash::digest_into(self, out)
+    }
+}
+
+// algorithms chosen at run time, e.g. from the header of a test vector file
+#[cfg(feature = "std")]
+impl<D: Digest + ?Sized> Digest for Box<D> {
+    fn update(&mut self, data: &[u8]) {
+        (**self).update(data)
+    }
+
+    fn finalize(&mut self) {
+        (**self).finalize()
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        (**self).digest_into(out)
     }
 }
 
@@ -222,22 +429,24 @@ where
     T: LowerHex,
     T: Display,
 {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let mut h = String::with_capacity(std::mem::size_of::<T>());
-        for i in 0..8 {
-            h.push_str(&format!(
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let words = self.digest_size / core::mem::size_of::<T>();
+        for word in self.hash.iter().take(words) {
+            write!(
+                f,
                 "{0:0width$x}",
-                self.hash[i],
-                width = 2 * std::mem::size_of::<T>()
-            ));
+                word,
+                width = 2 * core::mem::size_of::<T>()
+            )?;
         }
-        write!(f, "{}", h)
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
 
     #[test]
     fn display() {
@@ -269,4 +478,91 @@ mod tests {
             "0000000000000000000000000000000000000000000000000000000000000000"
         );
     }
+
+    #[test]
+    fn update_finalize() {
+        let msg = [b'a'; 1000];
+
+        // one shot
+        let mut one_shot = Sha256::new();
+        one_shot.update(&msg);
+        one_shot.finalize();
+
+        // chunks of various sizes, not aligned on block boundaries
+        for chunk_size in &[1, 7, 63, 64, 65, 200] {
+            let mut h = Sha256::new();
+            for chunk in msg.chunks(*chunk_size) {
+                h.update(chunk);
+            }
+            h.finalize();
+            assert_eq!(h.length, 1000);
+            assert_eq!(h.to_string(), one_shot.to_string());
+        }
+
+        // digest as bytes
+        let digest: [u8; 32] = one_shot.digest();
+        assert_eq!(digest[0], 0x41);
+        assert_eq!(digest[31], 0xa3);
+
+        // truncated digest
+        let digest: [u8; 5] = one_shot.digest();
+        assert_eq!(digest, [0x41, 0xed, 0xec, 0xe4, 0x2d]);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn buffer_size() {
+        let msg: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+
+        let mut default = Sha512::new();
+        assert_eq!(default.buffer_size, super::DEFAULT_BUFFER_SIZE);
+        let _ = default.message_hash(100_000, std::io::Cursor::new(&msg));
+
+        for &size in &[1, 100, 128, 1000, 1 << 20] {
+            let mut h = Sha512::new().with_buffer_size(size);
+            let result = h.message_hash(100_000, std::io::Cursor::new(&msg));
+            assert!(result.is_ok());
+            assert_eq!(h.to_string(), default.to_string());
+        }
+    }
+
+    #[test]
+    fn fork() {
+        let mut transcript = Sha256::new();
+        transcript.update(b"abc");
+
+        // digest of the prefix
+        let mut prefix = transcript.fork();
+        prefix.finalize();
+        assert_eq!(
+            prefix.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        // the original is not affected
+        transcript.update(b"dbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        transcript.finalize();
+        assert_eq!(
+            transcript.to_string(),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+
+        // clone of a sha512 in the middle of a block
+        let mut sha512 = Sha512::new();
+        sha512.update(&[b'a'; 200]);
+        let mut cloned = sha512.clone();
+        sha512.update(&[b'a'; 800]);
+        cloned.update(&[b'a'; 800]);
+        sha512.finalize();
+        cloned.finalize();
+        assert_eq!(cloned.to_string(), sha512.to_string());
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn message_hash_wrong_length() {
+        let mut h = Sha512::new();
+        let result = h.message_hash(4, std::io::Cursor::new(b"abc"));
+        assert!(result.is_err());
+    }
 }
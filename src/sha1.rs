@@ -0,0 +1,438 @@
+use crate::{
+    scramble::{Scramble, ScramblePool},
+    sha::{Hash, DEFAULT_BUFFER_SIZE},
+};
+
+// aliases for sha values: same block size and length padding as SHA-256, but 80 rounds
+pub type Sha1 = Hash<u32, 64, 80>;
+
+// initial hash value H(0): only 5 words are used
+pub const IV: [u32; 8] = [
+    0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476, 0xc3d2e1f0, 0, 0, 0,
+];
+
+// one constant for each group of 20 rounds
+const K: [u32; 4] = [0x5a827999, 0x6ed9eba1, 0x8f1bbcdc, 0xca62c1d6];
+
+// SHA-1 state is 5 words: a, b, c, d, e
+type State = [u32; 5];
+
+impl Hash<u32, 64, 80> {
+    pub fn new() -> Self {
+        Hash {
+            hash: IV,
+            k_constants: Self::k_constants(),
+            // only Ch and Maj are shared with SHA-2: the σ and Σ functions are those of SHA-256,
+            // unused by the SHA-1 rounds which rotate left instead
+            scramble_funcs: ScramblePool::<u32> {
+                ch: Scramble::<u32>::Ch,
+                maj: Scramble::<u32>::Maj,
+                σ0: Scramble::<u32>::σ::<7, 18, 3>,
+                σ1: Scramble::<u32>::σ::<17, 19, 10>,
+                Σ0: Scramble::<u32>::Σ::<2, 13, 22>,
+                Σ1: Scramble::<u32>::Σ::<6, 11, 25>,
+            },
+            block: [0u8; 64],
+            block_len: 0,
+            length: 0,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            compress_func: Self::sha1_compress,
+            digest_size: 20,
+            state_words: 5,
+            collision_detected: false,
+            rounds: 80,
+            standard: true,
+        }
+    }
+
+    // every block is checked for the counter-cryptanalysis of near-collision attacks like SHAttered.
+    // collision_detected is then set if a block is part of such an attack. The digest itself is
+    // unchanged. Hashing is much slower since each block is recompressed for every disturbance vector
+    pub fn with_collision_detection(mut self) -> Self {
+        self.compress_func = Self::sha1_dc_compress;
+        self
+    }
+
+    // K0 to K79
+    pub fn k_constants() -> [u32; 80] {
+        let mut k_constants = [0u32; 80];
+        for (i, k) in k_constants.iter_mut().enumerate() {
+            *k = K[i / 20];
+        }
+        k_constants
+    }
+
+    // W0 to W79
+    fn sha1_message_schedule(&self, block: &[u8]) -> [u32; 80] {
+        let mut w = [0u32; 80];
+
+        for (word, chunk) in w.iter_mut().zip(block.chunks(4)) {
+            *word = u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        }
+
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        w
+    }
+
+    // the function used by round t: Ch, Parity, Maj, Parity
+    fn sha1_f(&self, t: usize, b: u32, c: u32, d: u32) -> u32 {
+        match t / 20 {
+            0 => (self.scramble_funcs.ch)(b, c, d),
+            2 => (self.scramble_funcs.maj)(b, c, d),
+            _ => Scramble::<u32>::Parity(b, c, d),
+        }
+    }
+
+    // round t
+    fn sha1_step(&self, t: usize, state: &mut State, w: u32) {
+        let [a, b, c, d, e] = *state;
+        let temp = a
+            .rotate_left(5)
+            .wrapping_add(self.sha1_f(t, b, c, d))
+            .wrapping_add(e)
+            .wrapping_add(self.k_constants[t])
+            .wrapping_add(w);
+        *state = [temp, a, b.rotate_left(30), c, d];
+    }
+
+    // inverse of round t: gives the state before the round from the state after the round
+    fn sha1_step_back(&self, t: usize, state: &mut State, w: u32) {
+        let [a1, b1, c1, d1, e1] = *state;
+        let (a, b, c, d) = (b1, c1.rotate_left(2), d1, e1);
+        let e = a1
+            .wrapping_sub(a.rotate_left(5))
+            .wrapping_sub(self.sha1_f(t, b, c, d))
+            .wrapping_sub(self.k_constants[t])
+            .wrapping_sub(w);
+        *state = [a, b, c, d, e];
+    }
+
+    // SHA-1 compression function
+    pub fn sha1_compress(&mut self, block: &[u8]) {
+        let w = self.sha1_message_schedule(block);
+
+        let mut state = [
+            self.hash[0],
+            self.hash[1],
+            self.hash[2],
+            self.hash[3],
+            self.hash[4],
+        ];
+        for (t, wt) in w.iter().enumerate() {
+            self.sha1_step(t, &mut state, *wt);
+        }
+
+        for (h, s) in self.hash.iter_mut().zip(state.iter()) {
+            *h = h.wrapping_add(*s);
+        }
+    }
+
+    // SHA-1 compression function with collision detection
+    fn sha1_dc_compress(&mut self, block: &[u8]) {
+        let w = self.sha1_message_schedule(block);
+
+        let ihv_in = [
+            self.hash[0],
+            self.hash[1],
+            self.hash[2],
+            self.hash[3],
+            self.hash[4],
+        ];
+
+        // states[t] is the state before round t
+        let mut states = [[0u32; 5]; 80];
+        let mut state = ihv_in;
+        for (t, wt) in w.iter().enumerate() {
+            states[t] = state;
+            self.sha1_step(t, &mut state, *wt);
+        }
+
+        let mut ihv_out = ihv_in;
+        for (h, s) in ihv_out.iter_mut().zip(state.iter()) {
+            *h = h.wrapping_add(*s);
+        }
+
+        // a block of an attack has a sibling block, given by the disturbance vector message
+        // difference, with the same state at step testt and which ends with the same hash value
+        for dv in SHA1_DVS.iter() {
+            let mut w2 = w;
+            for (wt, dm) in w2.iter_mut().zip(dv.dm.iter()) {
+                *wt ^= dm;
+            }
+
+            if self.sha1_recompress(dv.testt, &states[dv.testt], &w2) == ihv_out {
+                self.collision_detected = true;
+            }
+        }
+
+        self.hash[..5].copy_from_slice(&ihv_out);
+    }
+
+    // hash value of the block w which has the given state before round testt. The input hash value
+    // is found by going backward from testt to round 0
+    fn sha1_recompress(&self, testt: usize, state: &State, w: &[u32; 80]) -> State {
+        let mut ihv_in = *state;
+        for t in (0..testt).rev() {
+            self.sha1_step_back(t, &mut ihv_in, w[t]);
+        }
+
+        let mut ihv_out = *state;
+        for (t, wt) in w.iter().enumerate().skip(testt) {
+            self.sha1_step(t, &mut ihv_out, *wt);
+        }
+
+        for (h, s) in ihv_out.iter_mut().zip(ihv_in.iter()) {
+            *h = h.wrapping_add(*s);
+        }
+        ihv_out
+    }
+}
+
+impl Default for Hash<u32, 64, 80> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// disturbance vector used by near-collision attacks: the state difference is 0 before round testt,
+// and dm is the expanded message XOR difference
+struct DisturbanceVector {
+    testt: usize,
+    dm: [u32; 80],
+}
+
+// the disturbance vectors of type I(K,b) and II(K,b) are defined by 16 consecutive words
+// DV[K..K+15]. The other words are given by the message expansion, computed from round -5 since the
+// local collisions starting in rounds -5..-1 are corrected in rounds 0..4
+const fn disturbance_vector(dv_type: u8, k: usize, b: u32, testt: usize) -> DisturbanceVector {
+    // dv[i] is DV[i - 5]
+    let mut dv = [0u32; 85];
+    dv[k + 15 + 5] = 1u32.rotate_left(b);
+    if dv_type == 2 {
+        dv[k + 1 + 5] = 1u32.rotate_left(31 + b);
+        dv[k + 3 + 5] = 1u32.rotate_left(31 + b);
+    }
+
+    // forward message expansion
+    let mut t = k + 16;
+    while t < 80 {
+        dv[t + 5] = (dv[t + 2] ^ dv[t - 3] ^ dv[t - 9] ^ dv[t - 11]).rotate_left(1);
+        t += 1;
+    }
+
+    // backward message expansion: DV[t-16] from DV[t], DV[t-3], DV[t-8], DV[t-14]
+    t = k + 15;
+    while t >= 11 {
+        dv[t - 11] = dv[t + 5].rotate_right(1) ^ dv[t + 2] ^ dv[t - 3] ^ dv[t - 9];
+        t -= 1;
+    }
+
+    // each disturbance is corrected by the next 5 message words (local collision)
+    let mut dm = [0u32; 80];
+    t = 0;
+    while t < 80 {
+        let i = t + 5;
+        dm[t] = dv[i]
+            ^ dv[i - 1].rotate_left(5)
+            ^ dv[i - 2]
+            ^ (dv[i - 3] ^ dv[i - 4] ^ dv[i - 5]).rotate_left(30);
+        t += 1;
+    }
+
+    DisturbanceVector { testt, dm }
+}
+
+// disturbance vectors checked by the collision detection, from Marc Stevens' sha1collisiondetection
+const SHA1_DVS: [DisturbanceVector; 35] = [
+    disturbance_vector(1, 43, 0, 58),
+    disturbance_vector(1, 44, 0, 58),
+    disturbance_vector(1, 45, 0, 58),
+    disturbance_vector(1, 46, 0, 58),
+    disturbance_vector(1, 46, 2, 58),
+    disturbance_vector(1, 47, 0, 58),
+    disturbance_vector(1, 47, 2, 58),
+    disturbance_vector(1, 48, 0, 58),
+    disturbance_vector(1, 48, 2, 58),
+    disturbance_vector(1, 49, 0, 58),
+    disturbance_vector(1, 49, 2, 58),
+    disturbance_vector(1, 50, 0, 65),
+    disturbance_vector(1, 50, 2, 65),
+    disturbance_vector(1, 51, 0, 65),
+    disturbance_vector(1, 51, 2, 65),
+    disturbance_vector(1, 52, 0, 65),
+    disturbance_vector(1, 53, 0, 65),
+    disturbance_vector(1, 54, 0, 65),
+    disturbance_vector(1, 55, 0, 65),
+    disturbance_vector(1, 56, 0, 65),
+    disturbance_vector(2, 45, 0, 58),
+    disturbance_vector(2, 46, 0, 58),
+    disturbance_vector(2, 46, 2, 58),
+    disturbance_vector(2, 47, 0, 58),
+    disturbance_vector(2, 48, 0, 58),
+    disturbance_vector(2, 49, 0, 58),
+    disturbance_vector(2, 49, 2, 58),
+    disturbance_vector(2, 50, 0, 65),
+    disturbance_vector(2, 50, 2, 65),
+    disturbance_vector(2, 51, 0, 65),
+    disturbance_vector(2, 52, 0, 65),
+    disturbance_vector(2, 53, 0, 65),
+    disturbance_vector(2, 54, 0, 65),
+    disturbance_vector(2, 55, 0, 65),
+    disturbance_vector(2, 56, 0, 65),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "std")]
+    use std::io::Cursor;
+
+    #[test]
+    fn test_vector_0() {
+        let mut hash = Sha1::new();
+        hash.finalize();
+
+        assert_eq!(hash.to_string(), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_vector_1() {
+        let msg = b"abc";
+        let cursor = Cursor::new(msg);
+        let mut hash = Sha1::new();
+        let result = hash.message_hash(3, cursor);
+        assert!(result.is_ok());
+
+        assert_eq!(hash.to_string(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+
+        let digest: [u8; 20] = hash.digest();
+        assert_eq!(digest[0], 0xa9);
+        assert_eq!(digest[19], 0x9d);
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_vector_2() {
+        let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+        let cursor = Cursor::new(msg);
+        let mut hash = Sha1::new();
+        let result = hash.message_hash(56, cursor);
+        assert!(result.is_ok());
+
+        assert_eq!(hash.to_string(), "84983e441c3bd26ebaae4aa1f95129e5e54670f1");
+    }
+
+    #[test]
+    #[cfg(feature = "std")]
+    fn test_vector_3() {
+        let msg = &[b'a'; 1_000_000];
+        let cursor = Cursor::new(msg);
+        let mut hash = Sha1::new();
+        let result = hash.message_hash(1_000_000, cursor);
+        assert!(result.is_ok());
+
+        assert_eq!(hash.to_string(), "34aa973cd4c4daa4f61eeb2bdbad27316534016f");
+    }
+
+    #[test]
+    fn step_back() {
+        let hash = Sha1::new();
+        let w: Vec<u32> = (0..80).map(|i| 0x01234567u32.wrapping_mul(i + 1)).collect();
+
+        let mut state = [1, 2, 3, 4, 5];
+        for (t, wt) in w.iter().enumerate() {
+            hash.sha1_step(t, &mut state, *wt);
+        }
+        for (t, wt) in w.iter().enumerate().rev() {
+            hash.sha1_step_back(t, &mut state, *wt);
+        }
+        assert_eq!(state, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn disturbance_vectors() {
+        for dv in SHA1_DVS.iter() {
+            // the message difference is an expanded message
+            for t in 16..80 {
+                assert_eq!(
+                    dv.dm[t],
+                    (dv.dm[t - 3] ^ dv.dm[t - 8] ^ dv.dm[t - 14] ^ dv.dm[t - 16]).rotate_left(1)
+                );
+            }
+            assert!(dv.dm.iter().any(|&d| d != 0));
+        }
+    }
+
+    #[test]
+    fn recompress() {
+        // without any message difference, the recompression gives the same hash value
+        let mut hash = Sha1::new();
+        let block: Vec<u8> = (0..64).collect();
+        let w = hash.sha1_message_schedule(&block);
+
+        let mut states = [[0u32; 5]; 80];
+        let mut state = [IV[0], IV[1], IV[2], IV[3], IV[4]];
+        for (t, wt) in w.iter().enumerate() {
+            states[t] = state;
+            hash.sha1_step(t, &mut state, *wt);
+        }
+
+        hash.sha1_compress(&block);
+        for &testt in &[58, 65] {
+            assert_eq!(
+                hash.sha1_recompress(testt, &states[testt], &w),
+                hash.hash[..5]
+            );
+        }
+    }
+
+    #[test]
+    fn collision_detection() {
+        let msg: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        let mut sha1 = Sha1::new();
+        sha1.update(&msg);
+        sha1.finalize();
+
+        // no false positive, same digest
+        let mut sha1_dc = Sha1::new().with_collision_detection();
+        sha1_dc.update(&msg);
+        sha1_dc.finalize();
+        assert!(!sha1_dc.collision_detected);
+        assert_eq!(sha1_dc.to_string(), sha1.to_string());
+    }
+
+    #[test]
+    fn shattered() {
+        // the first 320 bytes of the SHAttered PDFs: the common prefix then the two near-collision
+        // blocks, after which both internal states are the same
+        let shattered = [
+            &include_bytes!("../tests/shattered-1.bin")[..],
+            &include_bytes!("../tests/shattered-2.bin")[..],
+        ];
+        assert_ne!(shattered[0], shattered[1]);
+
+        for msg in shattered {
+            let mut sha1 = Sha1::new();
+            sha1.update(msg);
+            sha1.finalize();
+            assert_eq!(sha1.to_string(), "f92d74e3874587aaf443d1db961d4e26dde13e9c");
+            assert!(!sha1.collision_detected);
+
+            let mut sha1_dc = Sha1::new().with_collision_detection();
+            sha1_dc.update(msg);
+            sha1_dc.finalize();
+            assert!(sha1_dc.collision_detected);
+            assert_eq!(sha1_dc.to_string(), sha1.to_string());
+
+            // the common prefix alone isn't detected
+            let mut prefix = Sha1::new().with_collision_detection();
+            prefix.update(&msg[..192]);
+            prefix.finalize();
+            assert!(!prefix.collision_detected);
+        }
+    }
+}
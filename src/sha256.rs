@@ -1,19 +1,37 @@
 use crate::{
     scramble::{Scramble, ScramblePool},
-    sha::Hash,
+    sha::{Hash, DEFAULT_BUFFER_SIZE},
 };
 
 // aliases for sha values
 pub type Sha256 = Hash<u32, 64, 64>;
 
+// initial hash value H(0)
+pub const IV: [u32; 8] = [
+    0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab, 0x5be0cd19,
+];
+
+// SHA-224 initial hash value
+pub const IV_224: [u32; 8] = [
+    0xc1059ed8, 0x367cd507, 0x3070dd17, 0xf70e5939, 0xffc00b31, 0x68581511, 0x64f98fa7, 0xbefa4fa4,
+];
+
+// the 64 round constants K0 to K63
+pub const K_CONSTANTS: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
 impl Hash<u32, 64, 64> {
     pub fn new() -> Self {
-        let iv: [u32; 8] = [
-            0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
-            0x5be0cd19,
-        ];
         Hash {
-            hash: iv,
+            hash: IV,
             k_constants: Self::k_constants(),
             scramble_funcs: ScramblePool::<u32> {
                 ch: Scramble::<u32>::Ch,
@@ -24,31 +42,160 @@ impl Hash<u32, 64, 64> {
                 Σ1: Scramble::<u32>::Σ::<6, 11, 25>,
             },
             block: [0u8; 64],
+            block_len: 0,
+            length: 0,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            compress_func: Self::sha2_compress,
+            digest_size: 32,
+            state_words: 8,
+            collision_detected: false,
+            rounds: 64,
+            standard: true,
+        }
+    }
+
+    // SHA-256 with another IV, truncated to 28 bytes
+    pub fn sha224() -> Self {
+        Hash {
+            hash: IV_224,
+            digest_size: 28,
+            ..Self::new()
         }
     }
 
     pub fn k_constants() -> [u32; 64] {
-        [
-            0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4,
-            0xab1c5ed5, 0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe,
-            0x9bdc06a7, 0xc19bf174, 0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f,
-            0x4a7484aa, 0x5cb0a9dc, 0x76f988da, 0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7,
-            0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967, 0x27b70a85, 0x2e1b2138, 0x4d2c6dfc,
-            0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85, 0xa2bfe8a1, 0xa81a664b,
-            0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070, 0x19a4c116,
-            0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
-            0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7,
-            0xc67178f2,
-        ]
+        K_CONSTANTS
+    }
+}
+
+impl Default for Hash<u32, 64, 64> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// compile-time version of the scrambling functions: function pointers can't be called in const fn
+const fn big_sigma(x: u32, a: u32, b: u32, c: u32) -> u32 {
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.rotate_right(c)
+}
+
+const fn small_sigma(x: u32, a: u32, b: u32, c: u32) -> u32 {
+    x.rotate_right(a) ^ x.rotate_right(b) ^ (x >> c)
+}
+
+// one block of sha256 calculation, usable in const contexts
+pub(crate) const fn const_block_hash(mut hash: [u32; 8], block: &[u8; 64]) -> [u32; 8] {
+    let mut w = [0u32; 64];
+    let mut i = 0;
+    while i < 16 {
+        w[i] = u32::from_be_bytes([
+            block[4 * i],
+            block[4 * i + 1],
+            block[4 * i + 2],
+            block[4 * i + 3],
+        ]);
+        i += 1;
+    }
+    while i < 64 {
+        w[i] = small_sigma(w[i - 2], 17, 19, 10)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(small_sigma(w[i - 15], 7, 18, 3))
+            .wrapping_add(w[i - 16]);
+        i += 1;
+    }
+
+    let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h) = (
+        hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7],
+    );
+
+    i = 0;
+    while i < 64 {
+        let ch = (e & f) ^ (!e & g);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t1 = h
+            .wrapping_add(big_sigma(e, 6, 11, 25))
+            .wrapping_add(ch)
+            .wrapping_add(K_CONSTANTS[i])
+            .wrapping_add(w[i]);
+        let t2 = big_sigma(a, 2, 13, 22).wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        i += 1;
+    }
+
+    hash[0] = hash[0].wrapping_add(a);
+    hash[1] = hash[1].wrapping_add(b);
+    hash[2] = hash[2].wrapping_add(c);
+    hash[3] = hash[3].wrapping_add(d);
+    hash[4] = hash[4].wrapping_add(e);
+    hash[5] = hash[5].wrapping_add(f);
+    hash[6] = hash[6].wrapping_add(g);
+    hash[7] = hash[7].wrapping_add(h);
+    hash
+}
+
+// sha256 digest computed at compile time, e.g.: const ID: [u8; 32] = sha256_const(b"abc");
+pub const fn sha256_const(message: &[u8]) -> [u8; 32] {
+    let length = message.len();
+
+    // 0x80 and the 64-bit length need at least 9 more bytes
+    let blocks = (length + 9).div_ceil(64);
+    let length_as_bytes = ((length as u64) * 8).to_be_bytes();
+
+    let mut hash = IV;
+    let mut n = 0;
+    while n < blocks {
+        // build the block, padding included
+        let mut block = [0u8; 64];
+        let mut i = 0;
+        while i < 64 {
+            let pos = n * 64 + i;
+            if pos < length {
+                block[i] = message[pos];
+            } else if pos == length {
+                block[i] = 0x80;
+            }
+            i += 1;
+        }
+
+        // last block ends with the message length in bits
+        if n == blocks - 1 {
+            i = 0;
+            while i < 8 {
+                block[56 + i] = length_as_bytes[i];
+                i += 1;
+            }
+        }
+
+        hash = const_block_hash(hash, &block);
+        n += 1;
+    }
+
+    // convert to big endian bytes
+    let mut digest = [0u8; 32];
+    let mut i = 0;
+    while i < 32 {
+        digest[i] = hash[i / 4].to_be_bytes()[i % 4];
+        i += 1;
     }
+    digest
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "std")]
     use std::io::Cursor;
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn block_padding_256() {
         // test "", length = 0
         let mut hash = Sha256::new();
@@ -94,6 +241,43 @@ mod tests {
         assert_eq!(additional_block[63], 0xc0);
     }
 
+    // hex string of a digest, to compare with the Display output
+    fn to_hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn const_hash() {
+        const EMPTY: [u8; 32] = sha256_const(b"");
+        const ABC: [u8; 32] = sha256_const(b"abc");
+        const TWO_BLOCKS: [u8; 32] =
+            sha256_const(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+
+        assert_eq!(
+            to_hex(&EMPTY),
+            "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+        );
+
+        // compare with the runtime calculation for all message lengths around block boundaries
+        let msg = [b'a'; 200];
+        for len in 0..msg.len() {
+            let mut hash = Sha256::new();
+            hash.update(&msg[..len]);
+            hash.finalize();
+            assert_eq!(to_hex(&sha256_const(&msg[..len])), hash.to_string());
+        }
+
+        let mut hash = Sha256::new();
+        hash.update(b"abc");
+        hash.finalize();
+        assert_eq!(to_hex(&ABC), hash.to_string());
+
+        let mut hash = Sha256::new();
+        hash.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        hash.finalize();
+        assert_eq!(to_hex(&TWO_BLOCKS), hash.to_string());
+    }
+
     #[test]
     fn test_vector_0() {
         let mut hash = Sha256::new();
@@ -107,6 +291,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_1() {
         let msg = b"abc";
         let cursor = Cursor::new(msg);
@@ -121,6 +306,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_2() {
         let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
         let cursor = Cursor::new(msg);
@@ -135,6 +321,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_3() {
         let msg = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
         let cursor = Cursor::new(msg);
@@ -149,6 +336,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_4() {
         let msg = &[b'a'; 1_000_000];
         let cursor = Cursor::new(msg);
@@ -161,4 +349,39 @@ mod tests {
             "cdc76e5c9914fb9281a1c7e284d73e67f1809a48a497200e046d39ccc7112cd0"
         );
     }
+
+    #[test]
+    fn sha224() {
+        let mut hash = Sha256::sha224();
+        hash.update(b"");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "d14a028c2a3a2bc9476102bb288234c415a2b01f828ea62ac5b3e42f"
+        );
+
+        let mut hash = Sha256::sha224();
+        hash.update(b"abc");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "23097d223405d8228642a477bda255b32aadbce4bda0b3f7e36c9da7"
+        );
+
+        let mut hash = Sha256::sha224();
+        hash.update(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "75388b16512776cc5dba5da1fd890150b0c6455cb4f58b1952522525"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "digest longer than 28 bytes")]
+    fn sha224_longer_digest() {
+        let mut hash = Sha256::sha224();
+        hash.finalize();
+        let _: [u8; 32] = hash.digest();
+    }
 }
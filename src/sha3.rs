@@ -0,0 +1,299 @@
+use core::fmt::Display;
+
+use crate::sha::Digest;
+
+// Keccak-f[1600] round constants
+const RC: [u64; 24] = [
+    0x0000000000000001,
+    0x0000000000008082,
+    0x800000000000808A,
+    0x8000000080008000,
+    0x000000000000808B,
+    0x0000000080000001,
+    0x8000000080008081,
+    0x8000000000008009,
+    0x000000000000008A,
+    0x0000000000000088,
+    0x0000000080008009,
+    0x000000008000000A,
+    0x000000008000808B,
+    0x800000000000008B,
+    0x8000000000008089,
+    0x8000000000008003,
+    0x8000000000008002,
+    0x8000000000000080,
+    0x000000000000800A,
+    0x800000008000000A,
+    0x8000000080008081,
+    0x8000000000008080,
+    0x0000000080000001,
+    0x8000000080008008,
+];
+
+// ρ rotation offsets, in the order of the π lane permutation
+const RHO: [u32; 24] = [
+    1, 3, 6, 10, 15, 21, 28, 36, 45, 55, 2, 14, 27, 41, 56, 8, 25, 43, 62, 18, 39, 61, 20, 44,
+];
+
+// π lane permutation, starting from lane 1
+const PI: [usize; 24] = [
+    10, 7, 11, 17, 18, 3, 5, 16, 8, 21, 24, 4, 15, 23, 19, 13, 12, 2, 20, 14, 22, 9, 6, 1,
+];
+
+// domain separation bits, followed by the first bit of the pad10*1 padding
+pub const SHA3_DOMAIN: u8 = 0x06;
+pub const SHAKE_DOMAIN: u8 = 0x1f;
+
+// Keccak-f[1600] permutation on the 5x5 lanes of 64 bits
+pub fn keccak_f(state: &mut [u64; 25]) {
+    for rc in RC.iter() {
+        // θ
+        let mut c = [0u64; 5];
+        for (x, column) in c.iter_mut().enumerate() {
+            *column = state[x] ^ state[x + 5] ^ state[x + 10] ^ state[x + 15] ^ state[x + 20];
+        }
+        for x in 0..5 {
+            let d = c[(x + 4) % 5] ^ c[(x + 1) % 5].rotate_left(1);
+            for y in 0..5 {
+                state[5 * y + x] ^= d;
+            }
+        }
+
+        // ρ and π
+        let mut current = state[1];
+        for (&lane, &offset) in PI.iter().zip(RHO.iter()) {
+            let next = state[lane];
+            state[lane] = current.rotate_left(offset);
+            current = next;
+        }
+
+        // χ
+        for y in 0..5 {
+            let mut row = [0u64; 5];
+            row.copy_from_slice(&state[5 * y..5 * y + 5]);
+            for x in 0..5 {
+                state[5 * y + x] = row[x] ^ (!row[(x + 1) % 5] & row[(x + 2) % 5]);
+            }
+        }
+
+        // ι
+        state[0] ^= rc;
+    }
+}
+
+// Keccak sponge for SHA-3 (fixed output) and SHAKE (extendable output)
+#[derive(Clone)]
+pub struct Sha3 {
+    pub state: [u64; 25],
+    pub rate: usize,        // in bytes: 200 - 2 * security strength
+    pub block_len: usize,   // bytes absorbed in the current block, or already squeezed
+    pub domain: u8,         // 0x06 for SHA-3, 0x1f for SHAKE
+    pub digest_size: usize, // output length in bytes
+}
+
+impl Sha3 {
+    pub fn new(rate: usize, domain: u8, digest_size: usize) -> Self {
+        debug_assert!(rate > 0 && rate < 200);
+        Sha3 {
+            state: [0u64; 25],
+            rate,
+            block_len: 0,
+            domain,
+            digest_size,
+        }
+    }
+
+    pub fn sha3_224() -> Self {
+        Self::new(144, SHA3_DOMAIN, 28)
+    }
+
+    pub fn sha3_256() -> Self {
+        Self::new(136, SHA3_DOMAIN, 32)
+    }
+
+    pub fn sha3_384() -> Self {
+        Self::new(104, SHA3_DOMAIN, 48)
+    }
+
+    pub fn sha3_512() -> Self {
+        Self::new(72, SHA3_DOMAIN, 64)
+    }
+
+    // extendable output functions: digest_size is the output length used by digest and Display,
+    // but any length can be read with squeeze()
+    pub fn shake128(digest_size: usize) -> Self {
+        Self::new(168, SHAKE_DOMAIN, digest_size)
+    }
+
+    pub fn shake256(digest_size: usize) -> Self {
+        Self::new(136, SHAKE_DOMAIN, digest_size)
+    }
+
+    // absorb data: each time rate bytes are xored into the state, the state is permuted
+    pub fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.state[self.block_len / 8] ^= (*byte as u64) << (8 * (self.block_len % 8));
+            self.block_len += 1;
+
+            if self.block_len == self.rate {
+                keccak_f(&mut self.state);
+                self.block_len = 0;
+            }
+        }
+    }
+
+    // pad10*1 with the domain bits, then permute. The output can then be read
+    pub fn finalize(&mut self) {
+        self.state[self.block_len / 8] ^= (self.domain as u64) << (8 * (self.block_len % 8));
+        self.state[(self.rate - 1) / 8] ^= 0x80u64 << (8 * ((self.rate - 1) % 8));
+        keccak_f(&mut self.state);
+        self.block_len = 0;
+    }
+
+    // read the next bytes of the output, once finalized
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        for byte in out.iter_mut() {
+            if self.block_len == self.rate {
+                keccak_f(&mut self.state);
+                self.block_len = 0;
+            }
+            *byte = (self.state[self.block_len / 8] >> (8 * (self.block_len % 8))) as u8;
+            self.block_len += 1;
+        }
+    }
+
+    // first bytes of the output, without consuming it: squeeze() must not have been called
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.clone().squeeze(out);
+    }
+
+    // hash value as a fixed array, e.g.: let digest: [u8; 32] = sha3.digest();
+    pub fn digest<const N: usize>(&self) -> [u8; N] {
+        let mut digest = [0u8; N];
+        self.digest_into(&mut digest);
+        digest
+    }
+}
+
+impl Digest for Sha3 {
+    fn update(&mut self, data: &[u8]) {
+        Sha3::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        Sha3::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        Sha3::digest_into(self, out)
+    }
+}
+
+// print out the first digest_size bytes of the output
+impl Display for Sha3 {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        let mut output = self.clone();
+        let mut byte = [0u8; 1];
+        for _ in 0..self.digest_size {
+            output.squeeze(&mut byte);
+            write!(f, "{:02x}", byte[0])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hash(mut sha3: Sha3, msg: &[u8]) -> String {
+        sha3.update(msg);
+        sha3.finalize();
+        sha3.to_string()
+    }
+
+    #[test]
+    fn keccak_f_zero() {
+        // first lanes of Keccak-f[1600] applied to the zero state
+        let mut state = [0u64; 25];
+        keccak_f(&mut state);
+        assert_eq!(state[0], 0xF1258F7940E1DDE7);
+        assert_eq!(state[1], 0x84D5CCF933C0478A);
+        assert_eq!(state[24], 0xEAF1FF7B5CECA249);
+    }
+
+    #[test]
+    fn test_vector_0() {
+        assert_eq!(
+            hash(Sha3::sha3_224(), b""),
+            "6b4e03423667dbb73b6e15454f0eb1abd4597f9a1b078e3f5b5a6bc7"
+        );
+        assert_eq!(
+            hash(Sha3::sha3_256(), b""),
+            "a7ffc6f8bf1ed76651c14756a061d662f580ff4de43b49fa82d80a4b80f8434a"
+        );
+        assert_eq!(
+            hash(Sha3::sha3_384(), b""),
+            "0c63a75b845e4f7d01107d852e4c2485c51a50aaaa94fc61995e71bbee983a2ac3713831264adb47fb6bd1e058d5f004"
+        );
+        assert_eq!(
+            hash(Sha3::sha3_512(), b""),
+            "a69f73cca23a9ac5c8b567dc185a756e97c982164fe25859e0d1dcc1475c80a615b2123af1f5f94c11e3e9402c3ac558f500199d95b6d3e301758586281dcd26"
+        );
+        assert_eq!(
+            hash(Sha3::shake128(32), b""),
+            "7f9c2ba4e88f827d616045507605853ed73b8093f6efbc88eb1a6eacfa66ef26"
+        );
+        assert_eq!(
+            hash(Sha3::shake256(64), b""),
+            "46b9dd2b0ba88d13233b3feb743eeb243fcd52ea62b81b82b50c27646ed5762fd75dc4ddd8c0f200cb05019d67b592f6fc821c49479ab48640292eacb3b7c4be"
+        );
+    }
+
+    #[test]
+    fn test_vector_1() {
+        assert_eq!(
+            hash(Sha3::sha3_256(), b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+        assert_eq!(
+            hash(Sha3::sha3_512(), b"abc"),
+            "b751850b1a57168a5693cd924b6b096e08f621827444f70d884f5d0240d2712e10e116e9192af3c91a7ec57647e3934057340b4cf408d5a56592f8274eec53f0"
+        );
+    }
+
+    #[test]
+    fn test_vector_2() {
+        // 1 million 'a' by chunks not aligned on the rate
+        let mut sha3 = Sha3::sha3_256();
+        for _ in 0..10_000 {
+            sha3.update(&[b'a'; 100]);
+        }
+        sha3.finalize();
+        assert_eq!(
+            sha3.to_string(),
+            "5c8875ae474a3634ba4fd55ec85bffd661f32aca75c6d699d0cdcb6c115891c1"
+        );
+    }
+
+    #[test]
+    fn squeeze() {
+        // reading the output by pieces, more than the rate
+        let mut shake = Sha3::shake128(500);
+        shake.update(b"abc");
+        shake.finalize();
+        let expected = shake.to_string();
+
+        let mut output = Vec::new();
+        let mut piece = [0u8; 7];
+        while output.len() < 500 {
+            shake.squeeze(&mut piece);
+            output.extend_from_slice(&piece);
+        }
+        output.truncate(500);
+        let output: String = output.iter().map(|b| format!("{:02x}", b)).collect();
+        assert_eq!(output, expected);
+        assert!(expected
+            .starts_with("5881092dd818bf5cf8a3ddb793fbcba74097d5c526a6d35f97b83351940f2cc8"));
+    }
+}
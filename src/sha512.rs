@@ -1,25 +1,123 @@
 use crate::{
     scramble::{Scramble, ScramblePool},
-    sha::Hash,
+    sha::{Hash, DEFAULT_BUFFER_SIZE},
 };
 
 // aliases for sha values
 pub type Sha512 = Hash<u64, 128, 80>;
 
+// initial hash value H(0)
+pub const IV: [u64; 8] = [
+    0x6A09E667F3BCC908,
+    0xBB67AE8584CAA73B,
+    0x3C6EF372FE94F82B,
+    0xA54FF53A5F1D36F1,
+    0x510E527FADE682D1,
+    0x9B05688C2B3E6C1F,
+    0x1F83D9ABFB41BD6B,
+    0x5BE0CD19137E2179,
+];
+
+// SHA-384 initial hash value
+pub const IV_384: [u64; 8] = [
+    0xCBBB9D5DC1059ED8,
+    0x629A292A367CD507,
+    0x9159015A3070DD17,
+    0x152FECD8F70E5939,
+    0x67332667FFC00B31,
+    0x8EB44A8768581511,
+    0xDB0C2E0D64F98FA7,
+    0x47B5481DBEFA4FA4,
+];
+
+// the 80 round constants K0 to K79
+pub const K_CONSTANTS: [u64; 80] = [
+    0x428A2F98D728AE22,
+    0x7137449123EF65CD,
+    0xB5C0FBCFEC4D3B2F,
+    0xE9B5DBA58189DBBC,
+    0x3956C25BF348B538,
+    0x59F111F1B605D019,
+    0x923F82A4AF194F9B,
+    0xAB1C5ED5DA6D8118,
+    0xD807AA98A3030242,
+    0x12835B0145706FBE,
+    0x243185BE4EE4B28C,
+    0x550C7DC3D5FFB4E2,
+    0x72BE5D74F27B896F,
+    0x80DEB1FE3B1696B1,
+    0x9BDC06A725C71235,
+    0xC19BF174CF692694,
+    0xE49B69C19EF14AD2,
+    0xEFBE4786384F25E3,
+    0x0FC19DC68B8CD5B5,
+    0x240CA1CC77AC9C65,
+    0x2DE92C6F592B0275,
+    0x4A7484AA6EA6E483,
+    0x5CB0A9DCBD41FBD4,
+    0x76F988DA831153B5,
+    0x983E5152EE66DFAB,
+    0xA831C66D2DB43210,
+    0xB00327C898FB213F,
+    0xBF597FC7BEEF0EE4,
+    0xC6E00BF33DA88FC2,
+    0xD5A79147930AA725,
+    0x06CA6351E003826F,
+    0x142929670A0E6E70,
+    0x27B70A8546D22FFC,
+    0x2E1B21385C26C926,
+    0x4D2C6DFC5AC42AED,
+    0x53380D139D95B3DF,
+    0x650A73548BAF63DE,
+    0x766A0ABB3C77B2A8,
+    0x81C2C92E47EDAEE6,
+    0x92722C851482353B,
+    0xA2BFE8A14CF10364,
+    0xA81A664BBC423001,
+    0xC24B8B70D0F89791,
+    0xC76C51A30654BE30,
+    0xD192E819D6EF5218,
+    0xD69906245565A910,
+    0xF40E35855771202A,
+    0x106AA07032BBD1B8,
+    0x19A4C116B8D2D0C8,
+    0x1E376C085141AB53,
+    0x2748774CDF8EEB99,
+    0x34B0BCB5E19B48A8,
+    0x391C0CB3C5C95A63,
+    0x4ED8AA4AE3418ACB,
+    0x5B9CCA4F7763E373,
+    0x682E6FF3D6B2B8A3,
+    0x748F82EE5DEFB2FC,
+    0x78A5636F43172F60,
+    0x84C87814A1F0AB72,
+    0x8CC702081A6439EC,
+    0x90BEFFFA23631E28,
+    0xA4506CEBDE82BDE9,
+    0xBEF9A3F7B2C67915,
+    0xC67178F2E372532B,
+    0xCA273ECEEA26619C,
+    0xD186B8C721C0C207,
+    0xEADA7DD6CDE0EB1E,
+    0xF57D4F7FEE6ED178,
+    0x06F067AA72176FBA,
+    0x0A637DC5A2C898A6,
+    0x113F9804BEF90DAE,
+    0x1B710B35131C471B,
+    0x28DB77F523047D84,
+    0x32CAAB7B40C72493,
+    0x3C9EBE0A15C9BEBC,
+    0x431D67C49C100D4C,
+    0x4CC5D4BECB3E42B6,
+    0x597F299CFC657E2A,
+    0x5FCB6FAB3AD6FAEC,
+    0x6C44198C4A475817,
+];
+
 impl Hash<u64, 128, 80> {
     pub fn new() -> Self {
-        let iv: [u64; 8] = [
-            0x6A09E667F3BCC908,
-            0xBB67AE8584CAA73B,
-            0x3C6EF372FE94F82B,
-            0xA54FF53A5F1D36F1,
-            0x510E527FADE682D1,
-            0x9B05688C2B3E6C1F,
-            0x1F83D9ABFB41BD6B,
-            0x5BE0CD19137E2179,
-        ];
         Hash {
-            hash: iv,
+            hash: IV,
             k_constants: Self::k_constants(),
             scramble_funcs: ScramblePool::<u64> {
                 ch: Scramble::<u64>::Ch,
@@ -30,101 +128,161 @@ impl Hash<u64, 128, 80> {
                 Σ1: Scramble::<u64>::Σ::<14, 18, 41>,
             },
             block: [0u8; 128],
+            block_len: 0,
+            length: 0,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            compress_func: Self::sha2_compress,
+            digest_size: 64,
+            state_words: 8,
+            collision_detected: false,
+            rounds: 80,
+            standard: true,
+        }
+    }
+
+    // SHA-512 with another IV, truncated to 48 bytes
+    pub fn sha384() -> Self {
+        Hash {
+            hash: IV_384,
+            digest_size: 48,
+            ..Self::new()
         }
     }
 
     pub fn k_constants() -> [u64; 80] {
-        [
-            0x428A2F98D728AE22,
-            0x7137449123EF65CD,
-            0xB5C0FBCFEC4D3B2F,
-            0xE9B5DBA58189DBBC,
-            0x3956C25BF348B538,
-            0x59F111F1B605D019,
-            0x923F82A4AF194F9B,
-            0xAB1C5ED5DA6D8118,
-            0xD807AA98A3030242,
-            0x12835B0145706FBE,
-            0x243185BE4EE4B28C,
-            0x550C7DC3D5FFB4E2,
-            0x72BE5D74F27B896F,
-            0x80DEB1FE3B1696B1,
-            0x9BDC06A725C71235,
-            0xC19BF174CF692694,
-            0xE49B69C19EF14AD2,
-            0xEFBE4786384F25E3,
-            0x0FC19DC68B8CD5B5,
-            0x240CA1CC77AC9C65,
-            0x2DE92C6F592B0275,
-            0x4A7484AA6EA6E483,
-            0x5CB0A9DCBD41FBD4,
-            0x76F988DA831153B5,
-            0x983E5152EE66DFAB,
-            0xA831C66D2DB43210,
-            0xB00327C898FB213F,
-            0xBF597FC7BEEF0EE4,
-            0xC6E00BF33DA88FC2,
-            0xD5A79147930AA725,
-            0x06CA6351E003826F,
-            0x142929670A0E6E70,
-            0x27B70A8546D22FFC,
-            0x2E1B21385C26C926,
-            0x4D2C6DFC5AC42AED,
-            0x53380D139D95B3DF,
-            0x650A73548BAF63DE,
-            0x766A0ABB3C77B2A8,
-            0x81C2C92E47EDAEE6,
-            0x92722C851482353B,
-            0xA2BFE8A14CF10364,
-            0xA81A664BBC423001,
-            0xC24B8B70D0F89791,
-            0xC76C51A30654BE30,
-            0xD192E819D6EF5218,
-            0xD69906245565A910,
-            0xF40E35855771202A,
-            0x106AA07032BBD1B8,
-            0x19A4C116B8D2D0C8,
-            0x1E376C085141AB53,
-            0x2748774CDF8EEB99,
-            0x34B0BCB5E19B48A8,
-            0x391C0CB3C5C95A63,
-            0x4ED8AA4AE3418ACB,
-            0x5B9CCA4F7763E373,
-            0x682E6FF3D6B2B8A3,
-            0x748F82EE5DEFB2FC,
-            0x78A5636F43172F60,
-            0x84C87814A1F0AB72,
-            0x8CC702081A6439EC,
-            0x90BEFFFA23631E28,
-            0xA4506CEBDE82BDE9,
-            0xBEF9A3F7B2C67915,
-            0xC67178F2E372532B,
-            0xCA273ECEEA26619C,
-            0xD186B8C721C0C207,
-            0xEADA7DD6CDE0EB1E,
-            0xF57D4F7FEE6ED178,
-            0x06F067AA72176FBA,
-            0x0A637DC5A2C898A6,
-            0x113F9804BEF90DAE,
-            0x1B710B35131C471B,
-            0x28DB77F523047D84,
-            0x32CAAB7B40C72493,
-            0x3C9EBE0A15C9BEBC,
-            0x431D67C49C100D4C,
-            0x4CC5D4BECB3E42B6,
-            0x597F299CFC657E2A,
-            0x5FCB6FAB3AD6FAEC,
-            0x6C44198C4A475817,
-        ]
+        K_CONSTANTS
+    }
+}
+
+impl Default for Hash<u64, 128, 80> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// compile-time version of the scrambling functions: function pointers can't be called in const fn
+const fn big_sigma(x: u64, a: u32, b: u32, c: u32) -> u64 {
+    x.rotate_right(a) ^ x.rotate_right(b) ^ x.rotate_right(c)
+}
+
+const fn small_sigma(x: u64, a: u32, b: u32, c: u32) -> u64 {
+    x.rotate_right(a) ^ x.rotate_right(b) ^ (x >> c)
+}
+
+// one block of sha512 calculation, usable in const contexts
+const fn const_block_hash(mut hash: [u64; 8], block: &[u8; 128]) -> [u64; 8] {
+    let mut w = [0u64; 80];
+    let mut i = 0;
+    while i < 16 {
+        let mut bytes = [0u8; 8];
+        let mut j = 0;
+        while j < 8 {
+            bytes[j] = block[8 * i + j];
+            j += 1;
+        }
+        w[i] = u64::from_be_bytes(bytes);
+        i += 1;
+    }
+    while i < 80 {
+        w[i] = small_sigma(w[i - 2], 19, 61, 6)
+            .wrapping_add(w[i - 7])
+            .wrapping_add(small_sigma(w[i - 15], 1, 8, 7))
+            .wrapping_add(w[i - 16]);
+        i += 1;
+    }
+
+    let (mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut h) = (
+        hash[0], hash[1], hash[2], hash[3], hash[4], hash[5], hash[6], hash[7],
+    );
+
+    i = 0;
+    while i < 80 {
+        let ch = (e & f) ^ (!e & g);
+        let maj = (a & b) ^ (a & c) ^ (b & c);
+        let t1 = h
+            .wrapping_add(big_sigma(e, 14, 18, 41))
+            .wrapping_add(ch)
+            .wrapping_add(K_CONSTANTS[i])
+            .wrapping_add(w[i]);
+        let t2 = big_sigma(a, 28, 34, 39).wrapping_add(maj);
+
+        h = g;
+        g = f;
+        f = e;
+        e = d.wrapping_add(t1);
+        d = c;
+        c = b;
+        b = a;
+        a = t1.wrapping_add(t2);
+        i += 1;
+    }
+
+    hash[0] = hash[0].wrapping_add(a);
+    hash[1] = hash[1].wrapping_add(b);
+    hash[2] = hash[2].wrapping_add(c);
+    hash[3] = hash[3].wrapping_add(d);
+    hash[4] = hash[4].wrapping_add(e);
+    hash[5] = hash[5].wrapping_add(f);
+    hash[6] = hash[6].wrapping_add(g);
+    hash[7] = hash[7].wrapping_add(h);
+    hash
+}
+
+// sha512 digest computed at compile time, e.g.: const ID: [u8; 64] = sha512_const(b"abc");
+pub const fn sha512_const(message: &[u8]) -> [u8; 64] {
+    let length = message.len();
+
+    // 0x80 and the 128-bit length need at least 17 more bytes
+    let blocks = (length + 17).div_ceil(128);
+    let length_as_bytes = ((length as u64) * 8).to_be_bytes();
+
+    let mut hash = IV;
+    let mut n = 0;
+    while n < blocks {
+        // build the block, padding included
+        let mut block = [0u8; 128];
+        let mut i = 0;
+        while i < 128 {
+            let pos = n * 128 + i;
+            if pos < length {
+                block[i] = message[pos];
+            } else if pos == length {
+                block[i] = 0x80;
+            }
+            i += 1;
+        }
+
+        // last block ends with the message length in bits (upper 64 bits are 0)
+        if n == blocks - 1 {
+            i = 0;
+            while i < 8 {
+                block[120 + i] = length_as_bytes[i];
+                i += 1;
+            }
+        }
+
+        hash = const_block_hash(hash, &block);
+        n += 1;
+    }
+
+    // convert to big endian bytes
+    let mut digest = [0u8; 64];
+    let mut i = 0;
+    while i < 64 {
+        digest[i] = hash[i / 8].to_be_bytes()[i % 8];
+        i += 1;
     }
+    digest
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[cfg(feature = "std")]
     use std::io::Cursor;
 
     #[test]
+    #[allow(clippy::needless_range_loop)]
     fn block_padding_512() {
         // test "", length = 0
         let mut hash = Sha512::new();
@@ -171,6 +329,42 @@ mod tests {
         assert_eq!(additional_block[127], 0x80);
     }
 
+    // hex string of a digest, to compare with the Display output
+    fn to_hex(digest: &[u8]) -> String {
+        digest.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    #[test]
+    fn const_hash() {
+        const EMPTY: [u8; 64] = sha512_const(b"");
+        const ABC: [u8; 64] = sha512_const(b"abc");
+        const TWO_BLOCKS: [u8; 64] = sha512_const(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu");
+
+        assert_eq!(
+            to_hex(&EMPTY),
+            "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e"
+        );
+
+        // compare with the runtime calculation for all message lengths around block boundaries
+        let msg = [b'a'; 300];
+        for len in 0..msg.len() {
+            let mut hash = Sha512::new();
+            hash.update(&msg[..len]);
+            hash.finalize();
+            assert_eq!(to_hex(&sha512_const(&msg[..len])), hash.to_string());
+        }
+
+        let mut hash = Sha512::new();
+        hash.update(b"abc");
+        hash.finalize();
+        assert_eq!(to_hex(&ABC), hash.to_string());
+
+        let mut hash = Sha512::new();
+        hash.update(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu");
+        hash.finalize();
+        assert_eq!(to_hex(&TWO_BLOCKS), hash.to_string());
+    }
+
     #[test]
     fn test_vector_0() {
         let mut hash = Sha512::new();
@@ -184,6 +378,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_1() {
         let msg = b"abc";
         let cursor = Cursor::new(msg);
@@ -198,6 +393,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_2() {
         let msg = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
         let cursor = Cursor::new(msg);
@@ -212,6 +408,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_3() {
         let msg = b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu";
         let cursor = Cursor::new(msg);
@@ -226,6 +423,7 @@ mod tests {
     }
 
     #[test]
+    #[cfg(feature = "std")]
     fn test_vector_4() {
         let msg = &[b'a'; 1_000_000];
         let cursor = Cursor::new(msg);
@@ -238,4 +436,31 @@ mod tests {
             "e718483d0ce769644e2e42c7bc15b4638e1f98b13b2044285632a803afa973ebde0ff244877ea60a4cb0432ce577c31beb009c5c2c49aa2e4eadb217ad8cc09b"
         );
     }
+
+    #[test]
+    fn sha384() {
+        let mut hash = Sha512::sha384();
+        hash.update(b"");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "38b060a751ac96384cd9327eb1b1e36a21fdb71114be07434c0cc7bf63f6e1da274edebfe76f65fbd51ad2f14898b95b"
+        );
+
+        let mut hash = Sha512::sha384();
+        hash.update(b"abc");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "cb00753f45a35e8bb5a03d699ac65007272c32ab0eded1631a8b605a43ff5bed8086072ba1e7cc2358baeca134c825a7"
+        );
+
+        let mut hash = Sha512::sha384();
+        hash.update(b"abcdefghbcdefghicdefghijdefghijkefghijklfghijklmghijklmnhijklmnoijklmnopjklmnopqklmnopqrlmnopqrsmnopqrstnopqrstu");
+        hash.finalize();
+        assert_eq!(
+            hash.to_string(),
+            "09330c33f71147e83d192fc782cd1b4753111b173b3b05d22fa08086e3b0f712fcc7c71a557e2db966c3e9fa91746039"
+        );
+    }
 }
@@ -0,0 +1,569 @@
+use core::fmt::Display;
+
+use crate::sha::Digest;
+use crate::sha3::Sha3;
+
+// NIST SP 800-185 functions derived from SHA-3: cSHAKE, KMAC, TupleHash and ParallelHash
+
+// domain separation bits of cSHAKE, followed by the first bit of the pad10*1 padding
+pub const CSHAKE_DOMAIN: u8 = 0x04;
+
+// left_encode(x): number of bytes n of x, then x big endian on n bytes
+fn left_encode(x: u64, buf: &mut [u8; 9]) -> &[u8] {
+    let n = (8 - x.leading_zeros() as usize / 8).max(1);
+    buf[0] = n as u8;
+    buf[1..=n].copy_from_slice(&x.to_be_bytes()[8 - n..]);
+    &buf[..=n]
+}
+
+// right_encode(x): x big endian on n bytes, then n
+fn right_encode(x: u64, buf: &mut [u8; 9]) -> &[u8] {
+    let n = (8 - x.leading_zeros() as usize / 8).max(1);
+    buf[..n].copy_from_slice(&x.to_be_bytes()[8 - n..]);
+    buf[n] = n as u8;
+    &buf[..=n]
+}
+
+// encode_string(s): bit length of s, then s
+fn absorb_encoded_string(sha3: &mut Sha3, s: &[u8]) {
+    let mut buf = [0u8; 9];
+    sha3.update(left_encode(8 * s.len() as u64, &mut buf));
+    sha3.update(s);
+}
+
+// bytepad(encode_string(s1) || ..., rate): strings padded with zeros up to a multiple of the rate.
+// The sponge is empty so the padding ends when the current block is full
+fn absorb_bytepad(sha3: &mut Sha3, strings: &[&[u8]]) {
+    let mut buf = [0u8; 9];
+    sha3.update(left_encode(sha3.rate as u64, &mut buf));
+    for s in strings {
+        absorb_encoded_string(sha3, s);
+    }
+    while sha3.block_len != 0 {
+        sha3.update(&[0]);
+    }
+}
+
+fn cshake(rate: usize, function_name: &[u8], customization: &[u8], digest_size: usize) -> Sha3 {
+    // without any name or customization, cSHAKE is SHAKE
+    if function_name.is_empty() && customization.is_empty() {
+        return Sha3::new(rate, crate::sha3::SHAKE_DOMAIN, digest_size);
+    }
+
+    let mut sha3 = Sha3::new(rate, CSHAKE_DOMAIN, digest_size);
+    absorb_bytepad(&mut sha3, &[function_name, customization]);
+    sha3
+}
+
+// customizable SHAKE: the result is a sponge already holding the function name and customization
+pub fn cshake128(function_name: &[u8], customization: &[u8], digest_size: usize) -> Sha3 {
+    cshake(168, function_name, customization, digest_size)
+}
+
+pub fn cshake256(function_name: &[u8], customization: &[u8], digest_size: usize) -> Sha3 {
+    cshake(136, function_name, customization, digest_size)
+}
+
+// output length appended at the end of KMAC, TupleHash and ParallelHash: 0 for the XOF variants
+fn absorb_output_length(sha3: &mut Sha3, xof: bool) {
+    let mut buf = [0u8; 9];
+    let length = if xof { 0 } else { 8 * sha3.digest_size as u64 };
+    sha3.update(right_encode(length, &mut buf));
+}
+
+// keyed MAC on cSHAKE
+#[derive(Clone)]
+pub struct Kmac {
+    sha3: Sha3,
+    xof: bool,
+}
+
+impl Kmac {
+    fn new(mut sha3: Sha3, key: &[u8]) -> Self {
+        absorb_bytepad(&mut sha3, &[key]);
+        Kmac { sha3, xof: false }
+    }
+
+    pub fn kmac128(key: &[u8], customization: &[u8], digest_size: usize) -> Self {
+        Self::new(cshake128(b"KMAC", customization, digest_size), key)
+    }
+
+    pub fn kmac256(key: &[u8], customization: &[u8], digest_size: usize) -> Self {
+        Self::new(cshake256(b"KMAC", customization, digest_size), key)
+    }
+
+    // KMACXOF: the output doesn't depend on its length, and can be read with squeeze()
+    pub fn with_xof(mut self) -> Self {
+        self.xof = true;
+        self
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        self.sha3.update(data);
+    }
+
+    pub fn finalize(&mut self) {
+        absorb_output_length(&mut self.sha3, self.xof);
+        self.sha3.finalize();
+    }
+
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        self.sha3.squeeze(out);
+    }
+
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.sha3.digest_into(out);
+    }
+
+    pub fn digest<const N: usize>(&self) -> [u8; N] {
+        self.sha3.digest()
+    }
+
+    // compare a received tag with the finalized MAC, without stopping at the first difference
+    pub fn verify(&self, tag: &[u8]) -> bool {
+        if tag.len() != self.sha3.digest_size {
+            return false;
+        }
+
+        let mut output = self.sha3.clone();
+        let mut byte = [0u8; 1];
+        let mut diff = 0u8;
+        for t in tag {
+            output.squeeze(&mut byte);
+            diff |= byte[0] ^ t;
+        }
+        diff == 0
+    }
+}
+
+impl Digest for Kmac {
+    fn update(&mut self, data: &[u8]) {
+        Kmac::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        Kmac::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        Kmac::digest_into(self, out)
+    }
+}
+
+impl Display for Kmac {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.sha3.fmt(f)
+    }
+}
+
+// hash of a sequence of byte strings: ("ab", "c") and ("a", "bc") give different hashes
+#[derive(Clone)]
+pub struct TupleHash {
+    sha3: Sha3,
+    xof: bool,
+}
+
+impl TupleHash {
+    pub fn tuple_hash128(customization: &[u8], digest_size: usize) -> Self {
+        TupleHash {
+            sha3: cshake128(b"TupleHash", customization, digest_size),
+            xof: false,
+        }
+    }
+
+    pub fn tuple_hash256(customization: &[u8], digest_size: usize) -> Self {
+        TupleHash {
+            sha3: cshake256(b"TupleHash", customization, digest_size),
+            xof: false,
+        }
+    }
+
+    // TupleHashXOF
+    pub fn with_xof(mut self) -> Self {
+        self.xof = true;
+        self
+    }
+
+    // add the next element of the tuple
+    pub fn push(&mut self, item: &[u8]) {
+        absorb_encoded_string(&mut self.sha3, item);
+    }
+
+    pub fn finalize(&mut self) {
+        absorb_output_length(&mut self.sha3, self.xof);
+        self.sha3.finalize();
+    }
+
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        self.sha3.squeeze(out);
+    }
+
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.sha3.digest_into(out);
+    }
+
+    pub fn digest<const N: usize>(&self) -> [u8; N] {
+        self.sha3.digest()
+    }
+}
+
+impl Display for TupleHash {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.sha3.fmt(f)
+    }
+}
+
+// the message is cut into blocks of block_size bytes, each one hashed independently with SHAKE,
+// then the block hashes are hashed. Blocks are hashed one after the other here, but the output
+// is the same as for a parallel implementation
+#[derive(Clone)]
+pub struct ParallelHash {
+    sha3: Sha3,
+    block: Sha3,
+    block_size: usize,
+    block_len: usize,
+    blocks: u64,
+    xof: bool,
+}
+
+impl ParallelHash {
+    fn new(sha3: Sha3, block: Sha3, block_size: usize) -> Self {
+        assert!(block_size > 0, "ParallelHash block size can't be 0");
+
+        let mut parallel_hash = ParallelHash {
+            sha3,
+            block,
+            block_size,
+            block_len: 0,
+            blocks: 0,
+            xof: false,
+        };
+
+        let mut buf = [0u8; 9];
+        parallel_hash
+            .sha3
+            .update(left_encode(block_size as u64, &mut buf));
+        parallel_hash
+    }
+
+    pub fn parallel_hash128(block_size: usize, customization: &[u8], digest_size: usize) -> Self {
+        Self::new(
+            cshake128(b"ParallelHash", customization, digest_size),
+            Sha3::shake128(32),
+            block_size,
+        )
+    }
+
+    pub fn parallel_hash256(block_size: usize, customization: &[u8], digest_size: usize) -> Self {
+        Self::new(
+            cshake256(b"ParallelHash", customization, digest_size),
+            Sha3::shake256(64),
+            block_size,
+        )
+    }
+
+    // ParallelHashXOF
+    pub fn with_xof(mut self) -> Self {
+        self.xof = true;
+        self
+    }
+
+    // the hash of a finished block is absorbed by the outer sponge
+    fn block_hash(&mut self) {
+        let block_init = Sha3::new(self.block.rate, self.block.domain, self.block.digest_size);
+        let mut block = core::mem::replace(&mut self.block, block_init);
+        block.finalize();
+
+        let mut digest = [0u8; 64];
+        let digest = &mut digest[..block.digest_size];
+        block.digest_into(digest);
+        self.sha3.update(digest);
+
+        self.block_len = 0;
+        self.blocks += 1;
+    }
+
+    pub fn update(&mut self, mut data: &[u8]) {
+        while !data.is_empty() {
+            let length = data.len().min(self.block_size - self.block_len);
+            self.block.update(&data[..length]);
+            self.block_len += length;
+            data = &data[length..];
+
+            if self.block_len == self.block_size {
+                self.block_hash();
+            }
+        }
+    }
+
+    pub fn finalize(&mut self) {
+        // last partial block
+        if self.block_len != 0 {
+            self.block_hash();
+        }
+
+        let mut buf = [0u8; 9];
+        self.sha3.update(right_encode(self.blocks, &mut buf));
+        absorb_output_length(&mut self.sha3, self.xof);
+        self.sha3.finalize();
+    }
+
+    pub fn squeeze(&mut self, out: &mut [u8]) {
+        self.sha3.squeeze(out);
+    }
+
+    pub fn digest_into(&self, out: &mut [u8]) {
+        self.sha3.digest_into(out);
+    }
+
+    pub fn digest<const N: usize>(&self) -> [u8; N] {
+        self.sha3.digest()
+    }
+}
+
+impl Digest for ParallelHash {
+    fn update(&mut self, data: &[u8]) {
+        ParallelHash::update(self, data)
+    }
+
+    fn finalize(&mut self) {
+        ParallelHash::finalize(self)
+    }
+
+    fn digest_into(&self, out: &mut [u8]) {
+        ParallelHash::digest_into(self, out)
+    }
+}
+
+impl Display for ParallelHash {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        self.sha3.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // sample data of the NIST SP 800-185 examples
+    fn data(len: u8) -> Vec<u8> {
+        (0..len).collect()
+    }
+
+    const KEY: [u8; 32] = [
+        0x40, 0x41, 0x42, 0x43, 0x44, 0x45, 0x46, 0x47, 0x48, 0x49, 0x4a, 0x4b, 0x4c, 0x4d, 0x4e,
+        0x4f, 0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57, 0x58, 0x59, 0x5a, 0x5b, 0x5c, 0x5d,
+        0x5e, 0x5f,
+    ];
+
+    #[test]
+    fn encodings() {
+        let mut buf = [0u8; 9];
+        assert_eq!(left_encode(0, &mut buf), &[1, 0]);
+        assert_eq!(left_encode(168, &mut buf), &[1, 168]);
+        assert_eq!(left_encode(256, &mut buf), &[2, 1, 0]);
+        assert_eq!(right_encode(0, &mut buf), &[0, 1]);
+        assert_eq!(right_encode(256, &mut buf), &[1, 0, 2]);
+        assert_eq!(
+            left_encode(u64::MAX, &mut buf),
+            &[8, 255, 255, 255, 255, 255, 255, 255, 255]
+        );
+    }
+
+    #[test]
+    fn cshake_samples() {
+        let mut cshake = cshake128(b"", b"Email Signature", 32);
+        cshake.update(&data(4));
+        cshake.finalize();
+        assert_eq!(
+            cshake.to_string(),
+            "c1c36925b6409a04f1b504fcbca9d82b4017277cb5ed2b2065fc1d3814d5aaf5"
+        );
+
+        let mut cshake = cshake128(b"", b"Email Signature", 32);
+        cshake.update(&data(200));
+        cshake.finalize();
+        assert_eq!(
+            cshake.to_string(),
+            "c5221d50e4f822d96a2e8881a961420f294b7b24fe3d2094baed2c6524cc166b"
+        );
+
+        let mut cshake = cshake256(b"", b"Email Signature", 64);
+        cshake.update(&data(4));
+        cshake.finalize();
+        assert_eq!(
+            cshake.to_string(),
+            "d008828e2b80ac9d2218ffee1d070c48b8e4c87bff32c9699d5b6896eee0edd164020e2be0560858d9c00c037e34a96937c561a74c412bb4c746469527281c8c"
+        );
+
+        let mut cshake = cshake256(b"", b"Email Signature", 64);
+        cshake.update(&data(200));
+        cshake.finalize();
+        assert_eq!(
+            cshake.to_string(),
+            "07dc27b11e51fbac75bc7b3c1d983e8b4b85fb1defaf218912ac86430273091727f42b17ed1df63e8ec118f04b23633c1dfb1574c8fb55cb45da8e25afb092bb"
+        );
+    }
+
+    #[test]
+    fn kmac_samples() {
+        let samples: [(Kmac, u8, &str); 6] = [
+            (
+                Kmac::kmac128(&KEY, b"", 32),
+                4,
+                "e5780b0d3ea6f7d3a429c5706aa43a00fadbd7d49628839e3187243f456ee14e",
+            ),
+            (
+                Kmac::kmac128(&KEY, b"My Tagged Application", 32),
+                4,
+                "3b1fba963cd8b0b59e8c1a6d71888b7143651af8ba0a7070c0979e2811324aa5",
+            ),
+            (
+                Kmac::kmac128(&KEY, b"My Tagged Application", 32),
+                200,
+                "1f5b4e6cca02209e0dcb5ca635b89a15e271ecc760071dfd805faa38f9729230",
+            ),
+            (
+                Kmac::kmac256(&KEY, b"My Tagged Application", 64),
+                4,
+                "20c570c31346f703c9ac36c61c03cb64c3970d0cfc787e9b79599d273a68d2f7f69d4cc3de9d104a351689f27cf6f5951f0103f33f4f24871024d9c27773a8dd",
+            ),
+            (
+                Kmac::kmac256(&KEY, b"", 64),
+                200,
+                "75358cf39e41494e949707927cee0af20a3ff553904c86b08f21cc414bcfd691589d27cf5e15369cbbff8b9a4c2eb17800855d0235ff635da82533ec6b759b69",
+            ),
+            (
+                Kmac::kmac256(&KEY, b"My Tagged Application", 64),
+                200,
+                "b58618f71f92e1d56c1b8c55ddd7cd188b97b4ca4d99831eb2699a837da2e4d970fbacfde50033aea585f1a2708510c32d07880801bd182898fe476876fc8965",
+            ),
+        ];
+
+        for (mut kmac, len, expected) in samples {
+            kmac.update(&data(len));
+            kmac.finalize();
+            assert_eq!(kmac.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn kmac_xof_samples() {
+        let mut kmac = Kmac::kmac128(&KEY, b"", 32).with_xof();
+        kmac.update(&data(4));
+        kmac.finalize();
+        assert_eq!(
+            kmac.to_string(),
+            "cd83740bbd92ccc8cf032b1481a0f4460e7ca9dd12b08a0c4031178bacd6ec35"
+        );
+
+        let mut kmac = Kmac::kmac256(&KEY, b"My Tagged Application", 64).with_xof();
+        kmac.update(&data(4));
+        kmac.finalize();
+        assert_eq!(
+            kmac.to_string(),
+            "1755133f1534752aad0748f2c706fb5c784512cab835cd15676b16c0c6647fa96faa7af634a0bf8ff6df39374fa00fad9a39e322a7c92065a64eb1fb0801eb2b"
+        );
+    }
+
+    #[test]
+    fn kmac_verify() {
+        let mut kmac = Kmac::kmac128(&KEY, b"", 32);
+        kmac.update(&data(4));
+        kmac.finalize();
+
+        let mut tag: [u8; 32] = kmac.digest();
+        assert!(kmac.verify(&tag));
+        assert!(!kmac.verify(&tag[..31]));
+        tag[31] ^= 1;
+        assert!(!kmac.verify(&tag));
+    }
+
+    #[test]
+    fn tuple_hash_samples() {
+        let tuple: [&[u8]; 3] = [
+            &[0x00, 0x01, 0x02],
+            &[0x10, 0x11, 0x12, 0x13, 0x14, 0x15],
+            &[0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27, 0x28],
+        ];
+
+        let samples: [(TupleHash, usize, &str); 4] = [
+            (
+                TupleHash::tuple_hash128(b"", 32),
+                2,
+                "c5d8786c1afb9b82111ab34b65b2c0048fa64e6d48e263264ce1707d3ffc8ed1",
+            ),
+            (
+                TupleHash::tuple_hash128(b"My Tuple App", 32),
+                2,
+                "75cdb20ff4db1154e841d758e24160c54bae86eb8c13e7f5f40eb35588e96dfb",
+            ),
+            (
+                TupleHash::tuple_hash128(b"My Tuple App", 32),
+                3,
+                "e60f202c89a2631eda8d4c588ca5fd07f39e5151998deccf973adb3804bb6e84",
+            ),
+            (
+                TupleHash::tuple_hash256(b"", 64),
+                2,
+                "cfb7058caca5e668f81a12a20a2195ce97a925f1dba3e7449a56f82201ec607311ac2696b1ab5ea2352df1423bde7bd4bb78c9aed1a853c78672f9eb23bbe194",
+            ),
+        ];
+
+        for (mut tuple_hash, items, expected) in samples {
+            for item in &tuple[..items] {
+                tuple_hash.push(item);
+            }
+            tuple_hash.finalize();
+            assert_eq!(tuple_hash.to_string(), expected);
+        }
+
+        let mut tuple_hash = TupleHash::tuple_hash128(b"", 32).with_xof();
+        tuple_hash.push(tuple[0]);
+        tuple_hash.push(tuple[1]);
+        tuple_hash.finalize();
+        assert_eq!(
+            tuple_hash.to_string(),
+            "2f103cd7c32320353495c68de1a8129245c6325f6f2a3d608d92179c96e68488"
+        );
+    }
+
+    #[test]
+    fn parallel_hash_samples() {
+        let msg = [
+            0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x10, 0x11, 0x12, 0x13, 0x14, 0x15,
+            0x16, 0x17, 0x20, 0x21, 0x22, 0x23, 0x24, 0x25, 0x26, 0x27,
+        ];
+
+        let samples: [(ParallelHash, &str); 4] = [
+            (
+                ParallelHash::parallel_hash128(8, b"", 32),
+                "ba8dc1d1d979331d3f813603c67f72609ab5e44b94a0b8f9af46514454a2b4f5",
+            ),
+            (
+                ParallelHash::parallel_hash128(8, b"Parallel Data", 32),
+                "fc484dcb3f84dceedc353438151bee58157d6efed0445a81f165e495795b7206",
+            ),
+            (
+                ParallelHash::parallel_hash256(8, b"", 64),
+                "bc1ef124da34495e948ead207dd9842235da432d2bbc54b4c110e64c451105531b7f2a3e0ce055c02805e7c2de1fb746af97a1dd01f43b824e31b87612410429",
+            ),
+            (
+                ParallelHash::parallel_hash128(8, b"", 32).with_xof(),
+                "fe47d661e49ffe5b7d999922c062356750caf552985b8e8ce6667f2727c3c8d3",
+            ),
+        ];
+
+        for (parallel_hash, expected) in samples {
+            // blocks split across updates give the same hash
+            for chunk_size in [1, 5, 8, 24] {
+                let mut parallel_hash = parallel_hash.clone();
+                for chunk in msg.chunks(chunk_size) {
+                    parallel_hash.update(chunk);
+                }
+                parallel_hash.finalize();
+                assert_eq!(parallel_hash.to_string(), expected);
+            }
+        }
+    }
+}
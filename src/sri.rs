@@ -0,0 +1,307 @@
+use std::fmt::Display;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::file::ReadMode;
+use crate::sha256::Sha256;
+use crate::sha512::Sha512;
+
+// Subresource Integrity (W3C SRI): integrity="sha384-<base64 digest>", possibly several
+// space-separated hashes of which only the strongest algorithm is checked
+
+// ordered from the weakest to the strongest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SriAlgorithm {
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl SriAlgorithm {
+    // algorithm names are case-insensitive
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "sha256" => Some(SriAlgorithm::Sha256),
+            "sha384" => Some(SriAlgorithm::Sha384),
+            "sha512" => Some(SriAlgorithm::Sha512),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SriAlgorithm::Sha256 => "sha256",
+            SriAlgorithm::Sha384 => "sha384",
+            SriAlgorithm::Sha512 => "sha512",
+        }
+    }
+
+    pub fn digest(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            SriAlgorithm::Sha256 => Sha256::new().digest_of(data),
+            SriAlgorithm::Sha384 => Sha512::sha384().digest_of(data),
+            SriAlgorithm::Sha512 => Sha512::new().digest_of(data),
+        }
+    }
+
+    pub fn file_digest<P: AsRef<Path>>(&self, path: P, mode: ReadMode) -> std::io::Result<Vec<u8>> {
+        match self {
+            SriAlgorithm::Sha256 => Sha256::new().file_digest(path, mode),
+            SriAlgorithm::Sha384 => Sha512::sha384().file_digest(path, mode),
+            SriAlgorithm::Sha512 => Sha512::new().file_digest(path, mode),
+        }
+    }
+}
+
+impl Display for SriAlgorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+// RFC 4648 base64 with padding
+pub fn to_base64(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+
+    for chunk in bytes.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let w = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(BASE64[(w >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+// base64 or base64url, with or without padding, as accepted by the SRI specification
+pub fn from_base64(encoded: &str) -> Option<Vec<u8>> {
+    let encoded = encoded.trim_end_matches('=');
+    let mut bytes = Vec::with_capacity(encoded.len() * 3 / 4);
+    let mut w = 0u32;
+    let mut bits = 0;
+
+    for c in encoded.bytes() {
+        let value = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            _ => return None,
+        };
+        w = w << 6 | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((w >> bits) as u8);
+            w &= (1 << bits) - 1;
+        }
+    }
+
+    // a single character left can't encode a byte
+    if bits >= 6 {
+        return None;
+    }
+    Some(bytes)
+}
+
+// one hash of the integrity metadata: the value is kept as given, options (after ?) are dropped
+#[derive(Debug, Clone, PartialEq)]
+pub struct Integrity {
+    pub algorithm: SriAlgorithm,
+    pub value: String,
+}
+
+impl Integrity {
+    pub fn of(algorithm: SriAlgorithm, data: &[u8]) -> Self {
+        Integrity {
+            algorithm,
+            value: to_base64(&algorithm.digest(data)),
+        }
+    }
+
+    pub fn of_file<P: AsRef<Path>>(
+        algorithm: SriAlgorithm,
+        path: P,
+        mode: ReadMode,
+    ) -> std::io::Result<Self> {
+        Ok(Integrity {
+            algorithm,
+            value: to_base64(&algorithm.file_digest(path, mode)?),
+        })
+    }
+
+    // None if the value is not base64
+    pub fn digest(&self) -> Option<Vec<u8>> {
+        from_base64(&self.value)
+    }
+
+    pub fn matches(&self, data: &[u8]) -> bool {
+        self.digest() == Some(self.algorithm.digest(data))
+    }
+}
+
+impl Display for Integrity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.algorithm, self.value)
+    }
+}
+
+impl FromStr for Integrity {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = |msg: &str| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("invalid integrity {}: {}", s, msg),
+            )
+        };
+
+        let expression = s.split('?').next().unwrap_or_default();
+        let (name, value) = expression
+            .split_once('-')
+            .ok_or_else(|| invalid("missing algorithm"))?;
+        let algorithm =
+            SriAlgorithm::from_name(name).ok_or_else(|| invalid("unsupported algorithm"))?;
+
+        Ok(Integrity {
+            algorithm,
+            value: value.to_string(),
+        })
+    }
+}
+
+// space-separated hashes, those of unknown algorithms being ignored
+pub fn parse_metadata(metadata: &str) -> Vec<Integrity> {
+    metadata
+        .split_ascii_whitespace()
+        .filter_map(|item| item.parse().ok())
+        .collect()
+}
+
+// hashes of the strongest algorithm found, e.g. only sha512 ones if any
+pub fn strongest(metadata: &[Integrity]) -> Vec<&Integrity> {
+    match metadata.iter().map(|i| i.algorithm).max() {
+        Some(algorithm) => metadata
+            .iter()
+            .filter(|i| i.algorithm == algorithm)
+            .collect(),
+        None => Vec::new(),
+    }
+}
+
+// the data matches one of the strongest hashes. Without any supported hash, the data is accepted as
+// per the specification
+pub fn verify(metadata: &str, data: &[u8]) -> bool {
+    let metadata = parse_metadata(metadata);
+    if metadata.is_empty() {
+        return true;
+    }
+    strongest(&metadata).iter().any(|i| i.matches(data))
+}
+
+// integrity attribute value with one hash for each algorithm
+pub fn integrity_metadata(algorithms: &[SriAlgorithm], data: &[u8]) -> String {
+    algorithms
+        .iter()
+        .map(|&algorithm| Integrity::of(algorithm, data).to_string())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // example of the specification
+    const SCRIPT: &[u8] = b"alert('Hello, world.');";
+    const SHA256: &str = "sha256-qznLcsROx4GACP2dm0UCKCzCG+HiZ1guq6ZZDob/Tng=";
+    const SHA384: &str = "sha384-H8BRh8j48O9oYatfu5AZzq6A9RINhZO5H16dQZngK7T62em8MUt1FLm52t+eX6xO";
+    const SHA512: &str =
+        "sha512-Q2bFTOhEALkN8hOms2FKTDLy7eugP2zFZ1T8LCvX42Fp3WoNr3bjZSAHeOsHrbV1Fu9/A0EzCinRE7Af1ofPrw==";
+
+    #[test]
+    fn base64() {
+        for (bytes, encoded) in [
+            (&b""[..], ""),
+            (b"f", "Zg=="),
+            (b"fo", "Zm8="),
+            (b"foo", "Zm9v"),
+            (b"foob", "Zm9vYg=="),
+            (b"fooba", "Zm9vYmE="),
+            (b"foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(to_base64(bytes), encoded);
+            assert_eq!(from_base64(encoded).unwrap(), bytes);
+        }
+
+        assert_eq!(from_base64("Zm9vYg").unwrap(), b"foob");
+        assert_eq!(from_base64("-_8").unwrap(), [0xfb, 0xff]);
+        assert!(from_base64("Zm9vY").is_none());
+        assert!(from_base64("Zm9v!").is_none());
+    }
+
+    #[test]
+    fn generate() {
+        assert_eq!(
+            Integrity::of(SriAlgorithm::Sha256, SCRIPT).to_string(),
+            SHA256
+        );
+        assert_eq!(
+            Integrity::of(SriAlgorithm::Sha384, SCRIPT).to_string(),
+            SHA384
+        );
+        assert_eq!(
+            Integrity::of(SriAlgorithm::Sha512, SCRIPT).to_string(),
+            SHA512
+        );
+        assert_eq!(
+            integrity_metadata(&[SriAlgorithm::Sha256, SriAlgorithm::Sha384], SCRIPT),
+            format!("{} {}", SHA256, SHA384)
+        );
+    }
+
+    #[test]
+    fn verify_metadata() {
+        assert!(verify(SHA384, SCRIPT));
+        assert!(!verify(SHA384, b"alert('Hello, world!');"));
+
+        // options and case-insensitive algorithm names
+        assert!(verify(&format!("{}?foo", SHA384), SCRIPT));
+        assert!(verify(&SHA384.replace("sha384", "SHA384"), SCRIPT));
+
+        // only the strongest algorithm counts: a wrong sha512 hash isn't saved by a valid sha256 one
+        let wrong512 = Integrity::of(SriAlgorithm::Sha512, b"other").to_string();
+        assert!(!verify(&format!("{} {}", SHA256, wrong512), SCRIPT));
+        assert!(verify(
+            &format!("{}  {}\t{}", wrong512, SHA256, SHA512),
+            SCRIPT
+        ));
+
+        let metadata = parse_metadata(&format!("md5-abc {} {} {}", SHA256, wrong512, SHA512));
+        assert_eq!(metadata.len(), 3);
+        assert_eq!(strongest(&metadata).len(), 2);
+
+        // nothing supported: accepted
+        assert!(verify("md5-1B2M2Y8AsgTpgAmY7PhCfg==", SCRIPT));
+        assert!(verify("", SCRIPT));
+
+        // base64url
+        let url = SHA512.replace('+', "-").replace('/', "_");
+        assert!(verify(&url, SCRIPT));
+        assert!(!verify("sha256-!!!", SCRIPT));
+    }
+}
@@ -0,0 +1,402 @@
+use std::fmt::LowerHex;
+use std::fs::File;
+use std::io::{Error, ErrorKind, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+
+// checkpoint layout, all integers big endian:
+// magic (4) | version (1) | word size (1) | block size (2) | rounds (2) | digest size (1) | flags (1)
+// | length (8) | block_len (2) | 8 hash words | block_len buffered bytes
+// followed for resumable_file_hash() by: file length (8) | n (1) | the last n hashed bytes
+const MAGIC: &[u8; 4] = b"SHAS";
+pub const STATE_VERSION: u8 = 2;
+const HEADER_SIZE: usize = 22;
+
+// flags
+const STANDARD: u8 = 0x01;
+const COLLISION_DETECTED: u8 = 0x02;
+
+// the last bytes hashed from a file, compared with the file when resuming
+const FILE_TAIL_SIZE: u64 = 32;
+
+// when hashing a file, the state is saved every CHECKPOINT_INTERVAL bytes
+pub const CHECKPOINT_INTERVAL: u64 = 64 * 1024 * 1024;
+
+fn invalid_state(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid hash state: {}", msg),
+    )
+}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS> {
+    // intermediate state of a running hash, to continue it later with restore_state(). The
+    // parameters of the Sha2Builder variants aren't saved, so only standard hashes can be
+    pub fn save_state(&self) -> std::io::Result<Vec<u8>>
+    where
+        T: Modular<T>,
+    {
+        if !self.standard {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the state of a non-standard hash can't be saved",
+            ));
+        }
+
+        let size = std::mem::size_of::<T>();
+        let mut state = Vec::with_capacity(HEADER_SIZE + 8 * size + self.block_len);
+
+        state.extend_from_slice(MAGIC);
+        state.push(STATE_VERSION);
+        state.push(size as u8);
+        state.extend_from_slice(&(BLOCKSIZE as u16).to_be_bytes());
+        state.extend_from_slice(&(self.rounds as u16).to_be_bytes());
+        state.push(self.digest_size as u8);
+        state.push(self.flags());
+        state.extend_from_slice(&self.length.to_be_bytes());
+        state.extend_from_slice(&(self.block_len as u16).to_be_bytes());
+
+        let mut word = vec![0u8; size];
+        for h in &self.hash {
+            h.to_bytes(&mut word);
+            state.extend_from_slice(&word);
+        }
+
+        state.extend_from_slice(&self.block[..self.block_len]);
+        Ok(state)
+    }
+
+    fn flags(&self) -> u8 {
+        let mut flags = 0;
+        if self.standard {
+            flags |= STANDARD;
+        }
+        if self.collision_detected {
+            flags |= COLLISION_DETECTED;
+        }
+        flags
+    }
+
+    // state of a hash of the file bytes, with what's needed to check it's the same file
+    fn save_file_state(&self, file_length: u64, tail: &[u8]) -> std::io::Result<Vec<u8>>
+    where
+        T: Modular<T>,
+    {
+        let mut state = self.save_state()?;
+        state.extend_from_slice(&file_length.to_be_bytes());
+        state.push(tail.len() as u8);
+        state.extend_from_slice(tail);
+        Ok(state)
+    }
+
+    // continue a hash from a state given by save_state(). The hash must be of the same variant
+    pub fn restore_state(&mut self, state: &[u8]) -> std::io::Result<()>
+    where
+        T: Modular<T>,
+    {
+        let size = std::mem::size_of::<T>();
+
+        if state.len() < HEADER_SIZE || &state[0..4] != MAGIC {
+            return Err(invalid_state("not a state"));
+        }
+        if state[4] != STATE_VERSION {
+            return Err(invalid_state(&format!("unsupported version {}", state[4])));
+        }
+
+        // the hash variant must be the same, including the digest size (e.g. SHA-384 and SHA-512).
+        // Non-standard variants differ by parameters not saved
+        if !self.standard || state[11] & STANDARD == 0 {
+            return Err(invalid_state("non-standard hashes can't be restored"));
+        }
+        let block_size = u16::from_be_bytes([state[6], state[7]]) as usize;
+        let rounds = u16::from_be_bytes([state[8], state[9]]) as usize;
+        if state[5] as usize != size
+            || block_size != BLOCKSIZE
+            || rounds != self.rounds
+            || state[10] as usize != self.digest_size
+        {
+            return Err(invalid_state("saved from another hash variant"));
+        }
+
+        let mut length = [0u8; 8];
+        length.copy_from_slice(&state[12..20]);
+        let length = u64::from_be_bytes(length);
+
+        let block_len = u16::from_be_bytes([state[20], state[21]]) as usize;
+        if block_len >= BLOCKSIZE || length % BLOCKSIZE as u64 != block_len as u64 {
+            return Err(invalid_state("inconsistent length"));
+        }
+        let state_size = HEADER_SIZE + 8 * size + block_len;
+        if state.len() != state_size && file_check(&state[state_size.min(state.len())..]).is_none()
+        {
+            return Err(invalid_state("wrong size"));
+        }
+
+        let words = &state[HEADER_SIZE..HEADER_SIZE + 8 * size];
+        for (h, chunk) in self.hash.iter_mut().zip(words.chunks(size)) {
+            *h = T::to_uint(chunk);
+        }
+        self.block[..block_len].copy_from_slice(&state[HEADER_SIZE + 8 * size..state_size]);
+        self.block_len = block_len;
+        self.length = length;
+        self.collision_detected = state[11] & COLLISION_DETECTED != 0;
+
+        Ok(())
+    }
+
+    // hash a file with a fresh hash, saving the state into state_path regularly. If state_path
+    // exists, hashing resumes from the saved state. The state file is removed once the hash is done
+    pub fn resumable_file_hash<P, S>(&mut self, path: P, state_path: S) -> std::io::Result<()>
+    where
+        P: AsRef<Path>,
+        S: AsRef<Path>,
+        T: Default,
+        T: Copy,
+        T: Modular<T>,
+        T: LowerHex,
+    {
+        // the file is hashed from the start or from the saved state, not after bytes already hashed
+        if self.length != 0 || self.block_len != 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the hash has already been updated",
+            ));
+        }
+        if !self.standard {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "the state of a non-standard hash can't be saved",
+            ));
+        }
+
+        let state_path = state_path.as_ref();
+        let mut file = File::open(path)?;
+        let file_length = file.metadata()?.len();
+
+        if state_path.exists() {
+            let state = std::fs::read(state_path)?;
+            self.restore_state(&state)?;
+
+            // the state must have been saved while hashing the same file
+            let size = HEADER_SIZE + 8 * std::mem::size_of::<T>() + self.block_len;
+            match file_check(&state[size..]) {
+                Some((length, tail))
+                    if length == file_length
+                        && self.length <= file_length
+                        && file_tail(&mut file, self.length)? == tail => {}
+                Some(_) => return Err(invalid_state("saved while hashing another file")),
+                None => return Err(invalid_state("not saved while hashing a file")),
+            }
+        }
+
+        // restart right after the bytes already hashed
+        file.seek(SeekFrom::Start(self.length))?;
+
+        let mut buffer = vec![0u8; self.buffer_size];
+        let mut last_checkpoint = self.length;
+
+        loop {
+            match file.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(bytes_read) => self.update(&buffer[..bytes_read]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+
+            if self.length - last_checkpoint >= CHECKPOINT_INTERVAL {
+                let tail = file_tail(&mut file, self.length)?;
+                write_atomically(state_path, &self.save_file_state(file_length, &tail)?)?;
+                last_checkpoint = self.length;
+            }
+        }
+
+        self.finalize();
+
+        if state_path.exists() {
+            std::fs::remove_file(state_path)?;
+        }
+        Ok(())
+    }
+
+    // save state into a file. A temporary file is renamed to not leave a partial state if interrupted
+    pub fn write_state<S: AsRef<Path>>(&self, state_path: S) -> std::io::Result<()>
+    where
+        T: Modular<T>,
+    {
+        write_atomically(state_path.as_ref(), &self.save_state()?)
+    }
+}
+
+fn write_atomically(state_path: &Path, state: &[u8]) -> std::io::Result<()> {
+    let tmp_path = state_path.with_extension("tmp");
+    std::fs::write(&tmp_path, state)?;
+    std::fs::rename(&tmp_path, state_path)
+}
+
+// file length and last hashed bytes appended by save_file_state(), if any
+fn file_check(trailer: &[u8]) -> Option<(u64, &[u8])> {
+    if trailer.len() < 9 || trailer.len() != 9 + trailer[8] as usize {
+        return None;
+    }
+    let mut length = [0u8; 8];
+    length.copy_from_slice(&trailer[..8]);
+    Some((u64::from_be_bytes(length), &trailer[9..]))
+}
+
+// the bytes of the file before offset, the file position being at offset afterwards
+fn file_tail(file: &mut File, offset: u64) -> std::io::Result<Vec<u8>> {
+    let size = offset.min(FILE_TAIL_SIZE);
+    let mut tail = vec![0u8; size as usize];
+    file.seek(SeekFrom::Start(offset - size))?;
+    file.read_exact(&mut tail)?;
+    Ok(tail)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::experimental::Sha2Builder;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+    use std::io::Write;
+
+    #[test]
+    fn split_and_resume() {
+        let msg: Vec<u8> = (0..10_000).map(|i| (i % 251) as u8).collect();
+
+        let mut one_shot = Sha512::new();
+        one_shot.update(&msg);
+        one_shot.finalize();
+
+        for &split in &[0, 1, 127, 128, 129, 5000, 10_000] {
+            let mut first = Sha512::new();
+            first.update(&msg[..split]);
+            let state = first.save_state().unwrap();
+
+            let mut second = Sha512::new();
+            assert!(second.restore_state(&state).is_ok());
+            second.update(&msg[split..]);
+            second.finalize();
+            assert_eq!(second.to_string(), one_shot.to_string());
+        }
+    }
+
+    #[test]
+    fn invalid_states() {
+        let mut sha256 = Sha256::new();
+        sha256.update(b"abc");
+        let state = sha256.save_state().unwrap();
+
+        // another variant
+        let mut sha512 = Sha512::new();
+        assert!(sha512.restore_state(&state).is_err());
+
+        // truncated
+        let mut sha256 = Sha256::new();
+        assert!(sha256.restore_state(&state[..state.len() - 1]).is_err());
+
+        // unknown version
+        let mut future = state.clone();
+        future[4] = STATE_VERSION + 1;
+        assert!(sha256.restore_state(&future).is_err());
+
+        assert!(sha256.restore_state(b"foo").is_err());
+
+        // same words, rounds and block size but another digest size or a non-standard hash
+        let mut sha384 = Sha512::sha384();
+        sha384.update(b"abc");
+        let mut sha512 = Sha512::new();
+        assert!(sha512.restore_state(&sha384.save_state().unwrap()).is_err());
+        let mut built = Sha2Builder::new(Sha512::new()).build();
+        assert!(built.restore_state(&sha512.save_state().unwrap()).is_err());
+        assert!(built.save_state().is_err());
+
+        // a state without the standard flag
+        let mut non_standard = sha512.save_state().unwrap();
+        non_standard[11] &= !STANDARD;
+        assert!(sha512.restore_state(&non_standard).is_err());
+    }
+
+    #[test]
+    fn flags() {
+        let mut sha384 = Sha512::sha384();
+        sha384.update(b"abc");
+        let mut restored = Sha512::sha384();
+        assert!(restored
+            .restore_state(&sha384.save_state().unwrap())
+            .is_ok());
+        sha384.finalize();
+        restored.finalize();
+        assert_eq!(restored.to_string(), sha384.to_string());
+
+        let mut sha1 = Sha1::new().with_collision_detection();
+        sha1.collision_detected = true;
+        let mut restored = Sha1::new().with_collision_detection();
+        assert!(restored.restore_state(&sha1.save_state().unwrap()).is_ok());
+        assert!(restored.collision_detected);
+    }
+
+    #[test]
+    fn resumable_file() {
+        let data: Vec<u8> = (0..100_000).map(|i| (i % 251) as u8).collect();
+
+        let dir = std::env::temp_dir();
+        let path = dir.join(format!("sha_state_test_file_{}", std::process::id()));
+        let state_path = path.with_extension("state");
+        let mut file = File::create(&path).unwrap();
+        file.write_all(&data).unwrap();
+        drop(file);
+
+        let mut expected = Sha256::new();
+        expected.update(&data);
+        expected.finalize();
+
+        // interrupted after 40_001 bytes
+        let mut interrupted = Sha256::new();
+        interrupted.update(&data[..40_001]);
+        let state = interrupted
+            .save_file_state(data.len() as u64, &data[40_001 - 32..40_001])
+            .unwrap();
+        std::fs::write(&state_path, &state).unwrap();
+
+        let mut sha256 = Sha256::new();
+        assert!(sha256.resumable_file_hash(&path, &state_path).is_ok());
+        assert_eq!(sha256.to_string(), expected.to_string());
+        assert!(!state_path.exists());
+
+        // without any state
+        let mut sha256 = Sha256::new();
+        assert!(sha256.resumable_file_hash(&path, &state_path).is_ok());
+        assert_eq!(sha256.to_string(), expected.to_string());
+
+        // a state of another file: other length, other bytes or no file check at all
+        let other_length = interrupted
+            .save_file_state(data.len() as u64 + 1, &state[state.len() - 32..])
+            .unwrap();
+        let other_bytes = interrupted
+            .save_file_state(data.len() as u64, &[0u8; 32])
+            .unwrap();
+        for state in [other_length, other_bytes, interrupted.save_state().unwrap()] {
+            std::fs::write(&state_path, &state).unwrap();
+            let mut sha256 = Sha256::new();
+            assert!(sha256.resumable_file_hash(&path, &state_path).is_err());
+        }
+
+        std::fs::remove_file(&state_path).unwrap();
+
+        // the hash must be fresh, whether a state exists or not
+        let mut sha256 = Sha256::new();
+        sha256.update(b"abc");
+        assert!(sha256.resumable_file_hash(&path, &state_path).is_err());
+        let built = Sha2Builder::new(Sha256::new()).build();
+        assert!(built
+            .clone()
+            .resumable_file_hash(&path, &state_path)
+            .is_err());
+
+        std::fs::remove_file(&path).unwrap();
+    }
+}
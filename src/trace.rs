@@ -0,0 +1,276 @@
+use core::fmt::LowerHex;
+#[cfg(feature = "std")]
+use std::fmt::Write;
+
+use crate::convert::Modular;
+use crate::sha::Hash;
+
+// round-by-round view of the SHA-2 compression function, as in the examples of FIPS 180-4
+
+// each method is called by sha2_rounds(): the message schedule W of a block, the working
+// variables a to h after each round, and the intermediate hash value once the block is hashed
+pub trait Observer<T> {
+    fn block(&mut self, _index: u64, _w: &[T]) {}
+    fn round(&mut self, _t: usize, _variables: &[T; 8]) {}
+    fn hash(&mut self, _hash: &[T; 8]) {}
+}
+
+// observer of the regular compression function: does nothing
+pub struct NoTrace;
+
+impl<T> Observer<T> for NoTrace {}
+
+impl<T, const BLOCKSIZE: usize, const ROUNDS: usize> Hash<T, BLOCKSIZE, ROUNDS>
+where
+    T: Default,
+    T: Copy,
+    T: Modular<T>,
+    T: LowerHex,
+{
+    // same as update() then finalize(), the observer being called for each block. The SHA-2
+    // rounds are traced, so SHA-1 is rejected
+    pub fn trace<O: Observer<T>>(&mut self, message: &[u8], observer: &mut O) {
+        assert!(self.state_words == 8, "only SHA-2 rounds can be traced");
+
+        // index of the next block, bytes waiting in block are already counted in length
+        let mut index = (self.length - self.block_len as u64) / BLOCKSIZE as u64;
+        self.length += message.len() as u64;
+
+        for byte in message {
+            self.block[self.block_len] = *byte;
+            self.block_len += 1;
+
+            if self.block_len == BLOCKSIZE {
+                let block = self.block;
+                self.sha2_rounds(&block, index, observer);
+                self.block_len = 0;
+                index += 1;
+            }
+        }
+
+        let additional_block = self.block_padding(self.block_len, self.length);
+        let block = self.block;
+        self.sha2_rounds(&block, index, observer);
+
+        if let Some(new_block) = additional_block {
+            self.sha2_rounds(&new_block, index + 1, observer);
+        }
+
+        self.block_len = 0;
+    }
+}
+
+// intermediate values of a single block
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BlockTrace<T> {
+    pub index: u64,
+    pub w: Vec<T>,
+    pub rounds: Vec<[T; 8]>, // a to h after each round
+    pub hash: [T; 8],
+}
+
+// observer keeping every value, to be printed afterwards as a table or as JSON
+#[cfg(feature = "std")]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recorder<T> {
+    pub blocks: Vec<BlockTrace<T>>,
+}
+
+#[cfg(feature = "std")]
+impl<T: Copy + Default> Observer<T> for Recorder<T> {
+    fn block(&mut self, index: u64, w: &[T]) {
+        self.blocks.push(BlockTrace {
+            index,
+            w: w.to_vec(),
+            ..Default::default()
+        });
+    }
+
+    fn round(&mut self, _t: usize, variables: &[T; 8]) {
+        if let Some(block) = self.blocks.last_mut() {
+            block.rounds.push(*variables);
+        }
+    }
+
+    fn hash(&mut self, hash: &[T; 8]) {
+        if let Some(block) = self.blocks.last_mut() {
+            block.hash = *hash;
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: LowerHex> Recorder<T> {
+    // 8 hex digits for u32, 16 for u64
+    fn hex(word: &T) -> String {
+        format!("{:01$x}", word, 2 * core::mem::size_of::<T>())
+    }
+
+    fn hex_words(words: &[T]) -> String {
+        words.iter().map(Self::hex).collect::<Vec<_>>().join(" ")
+    }
+
+    // FIPS 180-4 appendix layout, blocks being numbered from 1:
+    //
+    // Block 1
+    // W[ 0.. 3] = 61626380 00000000 00000000 00000000
+    // ...
+    //              a        b        c        d        e        f        g        h
+    // t= 0: 5d6aebcd 6a09e667 bb67ae85 3c6ef372 fa2a4622 510e527f 9b05688c 1f83d9ab
+    // ...
+    // H(1) = ba7816bf 8f01cfea 414140de 5dae2223 b00361a3 96177a9c b410ff61 f20015ad
+    pub fn to_table(&self) -> String {
+        let width = 2 * core::mem::size_of::<T>();
+        let mut table = String::new();
+
+        for block in &self.blocks {
+            let _ = writeln!(table, "Block {}", block.index + 1);
+
+            for (i, words) in block.w.chunks(4).enumerate() {
+                let _ = writeln!(
+                    table,
+                    "W[{:2}..{:2}] = {}",
+                    4 * i,
+                    4 * i + words.len() - 1,
+                    Self::hex_words(words)
+                );
+            }
+
+            // column names are right-aligned on the values
+            table.push_str("     ");
+            for name in ["a", "b", "c", "d", "e", "f", "g", "h"] {
+                let _ = write!(table, " {:>1$}", name, width);
+            }
+            table.push('\n');
+
+            for (t, variables) in block.rounds.iter().enumerate() {
+                let _ = writeln!(table, "t={:2}: {}", t, Self::hex_words(variables));
+            }
+
+            let _ = writeln!(
+                table,
+                "H({}) = {}",
+                block.index + 1,
+                Self::hex_words(&block.hash)
+            );
+        }
+
+        table
+    }
+
+    // same values as hex strings: {"blocks":[{"block":1,"w":[...],"rounds":[[...],...],"hash":[...]}]}
+    pub fn to_json(&self) -> String {
+        let array = |words: &[T]| {
+            let words: Vec<String> = words
+                .iter()
+                .map(|w| format!("\"{}\"", Self::hex(w)))
+                .collect();
+            format!("[{}]", words.join(","))
+        };
+
+        let blocks: Vec<String> = self
+            .blocks
+            .iter()
+            .map(|block| {
+                let rounds: Vec<String> = block.rounds.iter().map(|r| array(r)).collect();
+                format!(
+                    "{{\"block\":{},\"w\":{},\"rounds\":[{}],\"hash\":{}}}",
+                    block.index + 1,
+                    array(&block.w),
+                    rounds.join(","),
+                    array(&block.hash)
+                )
+            })
+            .collect();
+
+        format!("{{\"blocks\":[{}]}}", blocks.join(","))
+    }
+}
+
+// the tests use the std Recorder
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::sha1::Sha1;
+    use crate::sha256::Sha256;
+    use crate::sha512::Sha512;
+
+    // FIPS 180-2 appendix B.1: SHA-256 of "abc"
+    #[test]
+    fn sha256_abc() {
+        let mut recorder = Recorder::default();
+        let mut sha256 = Sha256::new();
+        sha256.trace(b"abc", &mut recorder);
+        assert_eq!(
+            sha256.to_string(),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+
+        assert_eq!(recorder.blocks.len(), 1);
+        let block = &recorder.blocks[0];
+        assert_eq!(block.w[0], 0x61626380);
+        assert_eq!(block.w[15], 0x00000018);
+        assert_eq!(block.rounds.len(), 64);
+        assert_eq!(
+            block.rounds[0],
+            [
+                0x5d6aebcd, 0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xfa2a4622, 0x510e527f, 0x9b05688c,
+                0x1f83d9ab
+            ]
+        );
+        assert_eq!(
+            block.rounds[63],
+            [
+                0x506e3058, 0xd39a2165, 0x04d24d6c, 0xb85e2ce9, 0x5ef50f24, 0xfb121210, 0x948d25b6,
+                0x961f4894
+            ]
+        );
+        assert_eq!(block.hash, sha256.hash);
+
+        let table = recorder.to_table();
+        assert!(table.starts_with("Block 1\nW[ 0.. 3] = 61626380 00000000 00000000 00000000\n"));
+        assert!(table.contains(
+            "\nt= 0: 5d6aebcd 6a09e667 bb67ae85 3c6ef372 fa2a4622 510e527f 9b05688c 1f83d9ab\n"
+        ));
+        assert!(table.ends_with(
+            "H(1) = ba7816bf 8f01cfea 414140de 5dae2223 b00361a3 96177a9c b410ff61 f20015ad\n"
+        ));
+
+        let json = recorder.to_json();
+        assert!(json.starts_with("{\"blocks\":[{\"block\":1,\"w\":[\"61626380\",\"00000000\""));
+        assert!(json.ends_with("\"b410ff61\",\"f20015ad\"]}]}"));
+    }
+
+    // 2-block message, continued after an update: same digest as the untraced hash
+    #[test]
+    fn running_hash() {
+        let message = b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq";
+
+        let mut recorder = Recorder::default();
+        let mut sha256 = Sha256::new();
+        sha256.update(&message[..10]);
+        sha256.trace(&message[10..], &mut recorder);
+        assert_eq!(
+            sha256.to_string(),
+            "248d6a61d20638b8e5c026930c3e6039a33ce45964ff2167f6ecedd419db06c1"
+        );
+        assert_eq!(recorder.blocks.len(), 2);
+        assert_eq!(recorder.blocks[1].index, 1);
+
+        let mut recorder = Recorder::default();
+        let mut sha512 = Sha512::new();
+        sha512.trace(b"abc", &mut recorder);
+        assert_eq!(recorder.blocks[0].rounds.len(), 80);
+        assert_eq!(recorder.blocks[0].w[0], 0x6162638000000000);
+        assert!(recorder
+            .to_table()
+            .contains("\nt= 0: f6afceb8bcfcddf5 6a09e667f3bcc908 "));
+    }
+
+    #[test]
+    #[should_panic(expected = "only SHA-2 rounds can be traced")]
+    fn sha1() {
+        Sha1::new().trace(b"abc", &mut Recorder::default());
+    }
+}
@@ -0,0 +1,454 @@
+use std::fmt::Display;
+use std::io::{Cursor, Error, ErrorKind};
+use std::path::Path;
+
+use crate::cavp::{parse, sha2_monte, sha3_monte, shake_monte, ResponseFile};
+use crate::sha::Digest;
+use crate::sha1::Sha1;
+use crate::sha256::Sha256;
+use crate::sha3::Sha3;
+use crate::sha512::Sha512;
+
+// runs test vector files, either CAVP .rsp or ACVP .json, against the matching algorithm
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Algorithm {
+    Sha1,
+    Sha224,
+    Sha256,
+    Sha384,
+    Sha512,
+    Sha3_224,
+    Sha3_256,
+    Sha3_384,
+    Sha3_512,
+    Shake128,
+    Shake256,
+}
+
+impl Algorithm {
+    // CAVP names, e.g.: SHA-256, SHA3-256, SHAKE128 and ACVP names, e.g.: SHA2-256, SHAKE-128
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "SHA-1" | "SHA1" => Some(Algorithm::Sha1),
+            "SHA-224" | "SHA2-224" | "SHA224" => Some(Algorithm::Sha224),
+            "SHA-256" | "SHA2-256" | "SHA256" => Some(Algorithm::Sha256),
+            "SHA-384" | "SHA2-384" | "SHA384" => Some(Algorithm::Sha384),
+            "SHA-512" | "SHA2-512" | "SHA512" => Some(Algorithm::Sha512),
+            "SHA3-224" | "SHA3_224" => Some(Algorithm::Sha3_224),
+            "SHA3-256" | "SHA3_256" => Some(Algorithm::Sha3_256),
+            "SHA3-384" | "SHA3_384" => Some(Algorithm::Sha3_384),
+            "SHA3-512" | "SHA3_512" => Some(Algorithm::Sha3_512),
+            "SHAKE128" | "SHAKE-128" => Some(Algorithm::Shake128),
+            "SHAKE256" | "SHAKE-256" => Some(Algorithm::Shake256),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Algorithm::Sha1 => "SHA-1",
+            Algorithm::Sha224 => "SHA-224",
+            Algorithm::Sha256 => "SHA-256",
+            Algorithm::Sha384 => "SHA-384",
+            Algorithm::Sha512 => "SHA-512",
+            Algorithm::Sha3_224 => "SHA3-224",
+            Algorithm::Sha3_256 => "SHA3-256",
+            Algorithm::Sha3_384 => "SHA3-384",
+            Algorithm::Sha3_512 => "SHA3-512",
+            Algorithm::Shake128 => "SHAKE128",
+            Algorithm::Shake256 => "SHAKE256",
+        }
+    }
+
+    pub fn is_shake(&self) -> bool {
+        matches!(self, Algorithm::Shake128 | Algorithm::Shake256)
+    }
+
+    // digest size in bytes, the output length for SHAKE
+    fn digest_size(&self, output_length: usize) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha224 => 28,
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha384 => 48,
+            Algorithm::Sha512 => 64,
+            Algorithm::Sha3_224 => 28,
+            Algorithm::Sha3_256 => 32,
+            Algorithm::Sha3_384 => 48,
+            Algorithm::Sha3_512 => 64,
+            Algorithm::Shake128 | Algorithm::Shake256 => output_length,
+        }
+    }
+
+    // a new hash of this algorithm. output_length is only used by SHAKE
+    pub fn new_digest(&self, output_length: usize) -> Box<dyn Digest> {
+        match self {
+            Algorithm::Sha1 => Box::new(Sha1::new()),
+            Algorithm::Sha224 => Box::new(Sha256::sha224()),
+            Algorithm::Sha256 => Box::new(Sha256::new()),
+            Algorithm::Sha384 => Box::new(Sha512::sha384()),
+            Algorithm::Sha512 => Box::new(Sha512::new()),
+            Algorithm::Sha3_224 => Box::new(Sha3::sha3_224()),
+            Algorithm::Sha3_256 => Box::new(Sha3::sha3_256()),
+            Algorithm::Sha3_384 => Box::new(Sha3::sha3_384()),
+            Algorithm::Sha3_512 => Box::new(Sha3::sha3_512()),
+            Algorithm::Shake128 => Box::new(Sha3::shake128(output_length)),
+            Algorithm::Shake256 => Box::new(Sha3::shake256(output_length)),
+        }
+    }
+
+    pub fn hash(&self, message: &[u8], output_length: usize) -> Vec<u8> {
+        let mut digest = self.new_digest(output_length);
+        digest.update(message);
+        digest.finalize();
+
+        let mut md = vec![0u8; self.digest_size(output_length)];
+        digest.digest_into(&mut md);
+        md
+    }
+
+    // checkpoints of the Monte Carlo test of this algorithm. Output lengths are in bytes
+    fn monte(
+        &self,
+        seed: &[u8],
+        lengths: Option<(usize, usize)>,
+        checkpoints: usize,
+    ) -> std::io::Result<Vec<Vec<u8>>> {
+        let (min, max) = lengths.unwrap_or_default();
+        if self.is_shake() && (min < 2 || max < min) {
+            return Err(invalid_vectors("invalid SHAKE Monte Carlo output lengths"));
+        }
+
+        let new = || self.new_digest(0);
+        Ok(match self {
+            Algorithm::Sha1
+            | Algorithm::Sha224
+            | Algorithm::Sha256
+            | Algorithm::Sha384
+            | Algorithm::Sha512 => sha2_monte(new, seed, checkpoints),
+            Algorithm::Shake128 => shake_monte(Sha3::shake128, seed, min, max, checkpoints),
+            Algorithm::Shake256 => shake_monte(Sha3::shake256, seed, min, max, checkpoints),
+            _ => sha3_monte(new, seed, checkpoints),
+        })
+    }
+}
+
+impl Display for Algorithm {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+// outcome of a test vector file
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub algorithm: Algorithm,
+    pub passed: usize,
+    pub failed: Vec<String>, // test case identifiers, e.g.: "Len = 8" or "tcId = 12"
+    pub skipped: usize,      // bit-oriented messages, unsupported test types
+}
+
+impl Report {
+    fn new(algorithm: Algorithm) -> Self {
+        Report {
+            algorithm,
+            passed: 0,
+            failed: Vec::new(),
+            skipped: 0,
+        }
+    }
+
+    fn check(&mut self, id: String, passed: bool) {
+        if passed {
+            self.passed += 1;
+        } else {
+            self.failed.push(id);
+        }
+    }
+
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+}
+
+impl Display for Report {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}: {} passed, {} failed, {} skipped",
+            self.algorithm,
+            self.passed,
+            self.failed.len(),
+            self.skipped
+        )
+    }
+}
+
+fn invalid_vectors(msg: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("invalid test vectors: {}", msg),
+    )
+}
+
+// algorithm from the title, e.g.: "SHA-256 ShortMsg" information, or from the digest length
+pub fn rsp_algorithm(rsp: &ResponseFile) -> Option<Algorithm> {
+    let title = rsp
+        .comments
+        .iter()
+        .find_map(|comment| comment.split('"').nth(1))
+        .and_then(|title| title.split_whitespace().next())
+        .and_then(Algorithm::from_name);
+    if title.is_some() {
+        return title;
+    }
+
+    // L is in bytes for SHA-1 and SHA-2, in bits for SHA-3
+    match rsp.parameter("L")? {
+        "20" => Some(Algorithm::Sha1),
+        "28" => Some(Algorithm::Sha224),
+        "32" => Some(Algorithm::Sha256),
+        "48" => Some(Algorithm::Sha384),
+        "64" => Some(Algorithm::Sha512),
+        "224" => Some(Algorithm::Sha3_224),
+        "256" => Some(Algorithm::Sha3_256),
+        "384" => Some(Algorithm::Sha3_384),
+        "512" => Some(Algorithm::Sha3_512),
+        _ => None,
+    }
+}
+
+pub fn verify_rsp(rsp: &ResponseFile) -> std::io::Result<Report> {
+    let algorithm =
+        rsp_algorithm(rsp).ok_or_else(|| invalid_vectors("unknown or unsupported algorithm"))?;
+    let mut report = Report::new(algorithm);
+
+    if let Some(seed) = &rsp.seed {
+        let bytes = |name| {
+            rsp.parameter(name)
+                .and_then(|bits| bits.parse::<usize>().ok())
+                .map(|bits| bits / 8)
+        };
+        let lengths =
+            bytes("Minimum Output Length (bits)").zip(bytes("Maximum Output Length (bits)"));
+
+        let results = algorithm.monte(seed, lengths, rsp.cases.len())?;
+        for (case, md) in rsp.cases.iter().zip(results) {
+            report.check(
+                format!("COUNT = {}", case.count.unwrap_or_default()),
+                md == case.md,
+            );
+        }
+        return Ok(report);
+    }
+
+    for case in &rsp.cases {
+        match (case.length, &case.message) {
+            (Some(length), Some(message)) if length % 8 == 0 => {
+                let md = algorithm.hash(message, case.md.len());
+                report.check(format!("Len = {}", length), md == case.md);
+            }
+            _ => report.skipped += 1,
+        }
+    }
+
+    Ok(report)
+}
+
+// ACVP vector set with the expected results, either alone or after the {"acvVersion"} header
+#[cfg(feature = "acvp")]
+pub fn verify_acvp(json: &str) -> std::io::Result<Report> {
+    use crate::cavp::from_hex;
+    use serde_json::Value;
+
+    let json: Value = serde_json::from_str(json).map_err(|e| invalid_vectors(&e.to_string()))?;
+    let vector_set = match &json {
+        Value::Array(values) => values.iter().find(|v| v.get("algorithm").is_some()),
+        value => Some(value),
+    }
+    .ok_or_else(|| invalid_vectors("no vector set"))?;
+
+    let algorithm = vector_set["algorithm"]
+        .as_str()
+        .and_then(Algorithm::from_name)
+        .ok_or_else(|| invalid_vectors("unknown or unsupported algorithm"))?;
+    let mut report = Report::new(algorithm);
+
+    let hex = |value: &Value| value.as_str().and_then(from_hex);
+    let number = |value: &Value| value.as_u64().map(|n| n as usize);
+
+    let groups = vector_set["testGroups"]
+        .as_array()
+        .ok_or_else(|| invalid_vectors("no test groups"))?;
+    for group in groups {
+        let tests = group["tests"]
+            .as_array()
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        for test in tests {
+            let id = format!("tcId = {}", test["tcId"]);
+
+            match group["testType"].as_str() {
+                Some("AFT") => match (hex(&test["msg"]), number(&test["len"]), hex(&test["md"])) {
+                    // a length beyond the message is a malformed case, reported as failed
+                    (Some(msg), Some(len), Some(md)) if len % 8 == 0 => report.check(
+                        id,
+                        len / 8 <= msg.len() && algorithm.hash(&msg[..len / 8], md.len()) == md,
+                    ),
+                    _ => report.skipped += 1,
+                },
+                // the alternate version for messages shorter than the digest is not supported
+                Some("MCT") if group["mctVersion"].as_str().unwrap_or("standard") == "standard" => {
+                    let seed = hex(&test["msg"]).ok_or_else(|| invalid_vectors(&id))?;
+                    let expected: Vec<Vec<u8>> = test["resultsArray"]
+                        .as_array()
+                        .map(Vec::as_slice)
+                        .unwrap_or_default()
+                        .iter()
+                        .map(|result| hex(&result["md"]).unwrap_or_default())
+                        .collect();
+                    let lengths = number(&group["minOutLen"])
+                        .zip(number(&group["maxOutLen"]))
+                        .map(|(min, max)| (min / 8, max / 8));
+
+                    let results = algorithm.monte(&seed, lengths, expected.len())?;
+                    report.check(id, results == expected);
+                }
+                // the message is the content repeated up to the full length
+                Some("LDT") => {
+                    let large = &test["largeMsg"];
+                    match (
+                        hex(&large["content"]),
+                        number(&large["fullLength"]),
+                        large["expansionTechnique"].as_str(),
+                        hex(&test["md"]),
+                    ) {
+                        (Some(content), Some(length), Some("repeating"), Some(md))
+                            if !content.is_empty() && length % 8 == 0 =>
+                        {
+                            let mut digest = algorithm.new_digest(md.len());
+                            let mut remaining = length / 8;
+                            while remaining > 0 {
+                                let chunk = remaining.min(content.len());
+                                digest.update(&content[..chunk]);
+                                remaining -= chunk;
+                            }
+                            digest.finalize();
+
+                            let mut result = vec![0u8; algorithm.digest_size(md.len())];
+                            digest.digest_into(&mut result);
+                            report.check(id, result == md);
+                        }
+                        _ => report.skipped += 1,
+                    }
+                }
+                _ => report.skipped += 1,
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+#[cfg(not(feature = "acvp"))]
+pub fn verify_acvp(_json: &str) -> std::io::Result<Report> {
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        "ACVP test vectors need the acvp feature",
+    ))
+}
+
+// .json files are ACVP vector sets, others CAVP response files
+pub fn verify_file<P: AsRef<Path>>(path: P) -> std::io::Result<Report> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+
+    if path.extension().is_some_and(|ext| ext == "json") {
+        verify_acvp(&content)
+    } else {
+        verify_rsp(&parse(Cursor::new(content))?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cavp::read_rsp_file;
+
+    #[test]
+    fn rsp_files() {
+        for (file, algorithm) in [
+            ("tests/SHA1ShortMsg.rsp", Algorithm::Sha1),
+            ("tests/SHA224ShortMsg.rsp", Algorithm::Sha224),
+            ("tests/SHA256ShortMsg.rsp", Algorithm::Sha256),
+            ("tests/SHA384Monte.rsp", Algorithm::Sha384),
+            ("tests/SHA512ShortMsg.rsp", Algorithm::Sha512),
+            ("tests/SHA3_256ShortMsg.rsp", Algorithm::Sha3_256),
+            ("tests/SHAKE128VariableOut.rsp", Algorithm::Shake128),
+            ("tests/hashlib/SHA3_384ShortMsg.rsp", Algorithm::Sha3_384),
+            ("tests/hashlib/SHAKE256ShortMsg.rsp", Algorithm::Shake256),
+            ("tests/hashlib/SHAKE128Monte.rsp", Algorithm::Shake128),
+        ] {
+            let report = verify_file(file).unwrap();
+            assert_eq!(report.algorithm, algorithm);
+            assert!(report.passed > 0);
+            assert!(report.is_success(), "{}", report);
+        }
+    }
+
+    #[test]
+    fn detection_without_title() {
+        let mut rsp = read_rsp_file("tests/hashlib/SHA3_224ShortMsg.rsp").unwrap();
+        rsp.comments.clear();
+        assert_eq!(rsp_algorithm(&rsp), Some(Algorithm::Sha3_224));
+
+        rsp.parameters.clear();
+        assert!(verify_rsp(&rsp).is_err());
+    }
+
+    #[test]
+    fn failed_cases() {
+        let mut rsp = read_rsp_file("tests/SHA256ShortMsg.rsp").unwrap();
+        rsp.cases[3].md[0] ^= 1;
+        rsp.cases[5].length = Some(7);
+
+        let report = verify_rsp(&rsp).unwrap();
+        assert_eq!(report.failed, vec!["Len = 24"]);
+        assert_eq!(report.skipped, 1);
+        assert_eq!(report.passed, rsp.cases.len() - 2);
+        assert!(!report.is_success());
+    }
+
+    #[test]
+    #[cfg(feature = "acvp")]
+    fn acvp_files() {
+        let report = verify_file("tests/SHA2-256.acvp.json").unwrap();
+        assert_eq!(report.algorithm, Algorithm::Sha256);
+        assert_eq!(
+            (report.passed, report.failed.len(), report.skipped),
+            (4, 0, 1)
+        );
+
+        let report = verify_file("tests/SHAKE-128.acvp.json").unwrap();
+        assert_eq!(report.algorithm, Algorithm::Shake128);
+        assert_eq!(
+            (report.passed, report.failed.len(), report.skipped),
+            (2, 0, 0)
+        );
+
+        assert!(verify_acvp("{\"algorithm\": \"MD5\"}").is_err());
+        assert!(verify_acvp("[").is_err());
+    }
+
+    #[test]
+    #[cfg(feature = "acvp")]
+    fn acvp_length_beyond_message() {
+        let json = r#"{"algorithm": "SHA2-256", "testGroups": [{"testType": "AFT", "tests": [
+            {"tcId": 1, "msg": "6162", "len": 24, "md": "00"}]}]}"#;
+        let report = verify_acvp(json).unwrap();
+        assert_eq!(report.failed, vec!["tcId = 1"]);
+        assert_eq!(report.passed, 0);
+    }
+}
@@ -1,102 +1,333 @@
-use std::fs::File;
+// the test vector files are read with std
+#![cfg(feature = "std")]
+
 use std::io::Cursor;
-use std::io::{BufRead, BufReader};
 
 extern crate sha;
+use crate::sha::cavp::{
+    read_drbg_file, read_rsp_file, sha2_monte, sha3_monte, shake_monte, to_hex, DrbgTestCase,
+};
+use crate::sha::drbg::{Drbg, HashDrbg, HmacDrbg};
+use crate::sha::sha1::Sha1;
 use crate::sha::sha256::Sha256;
+use crate::sha::sha3::Sha3;
 use crate::sha::sha512::Sha512;
 
-#[derive(Debug)]
-struct TestCase {
-    message: Vec<u8>,
-    md: String,
-}
+// byte-oriented messages of a ShortMsg or LongMsg response file, with the empty message
+fn messages(response_file: &str) -> Vec<(Vec<u8>, String)> {
+    let rsp = read_rsp_file(response_file).expect("unable to read response file");
+    assert!(!rsp.cases.is_empty());
 
-// just return the trimmed string after = sign
-fn strip(text: &str) -> String {
-    let s: String = text.split('=').skip(1).collect();
-    s.trim().to_string()
+    rsp.cases
+        .into_iter()
+        .filter(|x| x.length.unwrap() % 8 == 0)
+        .map(|x| (x.message.unwrap(), to_hex(&x.md)))
+        .collect()
 }
 
-// reads the response file
-fn read_rsp_file(response_file: &str) -> Vec<TestCase> {
-    let mut tc: Vec<TestCase> = Vec::new();
-    let mut msg = Vec::new();
-    let mut message_length: usize = 0;
-
-    let file = File::open(response_file).expect("file not found!");
-    let reader = BufReader::new(file);
-
-    for (n, s) in reader.lines().enumerate() {
-        let line = s.unwrap();
-        println!("n={}, line=<{}>", n + 1, line);
-
-        // grab length
-        if line.starts_with("Len =") {
-            let l = strip(&line);
-            message_length = usize::from_str_radix(&l, 10).expect("unable to convert to usize");
-
-            // len is expressed in bytes
-            assert_eq!(message_length % 8, 0);
-            continue;
-        }
-
-        // grab the message and convert it to u8
-        if line.starts_with("Msg =") && message_length != 0 {
-            let bytes = strip(&line);
-            assert_eq!(bytes.len(), 2 * message_length / 8);
-
-            // convert string to Vec<u8>
-            msg = bytes
-                .as_bytes()
-                .chunks(2)
-                .map(std::str::from_utf8)
-                .map(|x| u8::from_str_radix(x.unwrap(), 16).unwrap())
-                .collect();
-            continue;
-        }
-
-        // grab the hash
-        if line.starts_with("MD =") && message_length != 0 {
-            let hash = strip(&line);
-            tc.push(TestCase {
-                message: msg.clone(),
-                md: hash.trim().to_string(),
-            });
-            msg.clear();
-            continue;
-        }
+fn test_sha1(response_file: &str) {
+    for (message, md) in messages(response_file) {
+        let mut sha1 = Sha1::new();
+        let cursor = Cursor::new(message.as_slice());
+        let result = sha1.message_hash(message.len() as u64, cursor);
+        assert!(result.is_ok());
+        assert_eq!(sha1.to_string(), md);
+    }
+}
+fn test_sha224(response_file: &str) {
+    for (message, md) in messages(response_file) {
+        let mut sha224 = Sha256::sha224();
+        let cursor = Cursor::new(message.as_slice());
+        let result = sha224.message_hash(message.len() as u64, cursor);
+        assert!(result.is_ok());
+        assert_eq!(sha224.to_string(), md);
     }
-    println!("end");
-
-    tc
 }
-
 fn test_sha256(response_file: &str) {
-    let tc = read_rsp_file(response_file);
-    for x in &tc {
+    for (message, md) in messages(response_file) {
         let mut sha256 = Sha256::new();
-        let cursor = Cursor::new(x.message.as_slice());
-        let result = sha256.message_hash(x.message.len() as u64, cursor);
+        let cursor = Cursor::new(message.as_slice());
+        let result = sha256.message_hash(message.len() as u64, cursor);
+        assert!(result.is_ok());
+        assert_eq!(sha256.to_string(), md);
+    }
+}
+fn test_sha384(response_file: &str) {
+    for (message, md) in messages(response_file) {
+        let mut sha384 = Sha512::sha384();
+        let cursor = Cursor::new(message.as_slice());
+        let result = sha384.message_hash(message.len() as u64, cursor);
         assert!(result.is_ok());
-        assert_eq!(sha256.to_string(), x.md);
+        assert_eq!(sha384.to_string(), md);
     }
 }
 fn test_sha512(response_file: &str) {
-    let tc = read_rsp_file(response_file);
-    for x in &tc {
+    for (message, md) in messages(response_file) {
         let mut sha512 = Sha512::new();
-        let cursor = Cursor::new(x.message.as_slice());
-        let result = sha512.message_hash(x.message.len() as u64, cursor);
+        let cursor = Cursor::new(message.as_slice());
+        let result = sha512.message_hash(message.len() as u64, cursor);
         assert!(result.is_ok());
-        assert_eq!(sha512.to_string(), x.md);
+        assert_eq!(sha512.to_string(), md);
+    }
+}
+
+// constructor gives the SHA-3 variant, and the output length for SHAKE
+fn test_sha3(response_file: &str, new: fn() -> Sha3) {
+    for (message, md) in messages(response_file) {
+        let mut sha3 = new();
+        sha3.update(&message);
+        sha3.finalize();
+        assert_eq!(sha3.to_string(), md);
     }
 }
 
 #[test]
 fn all_sha() {
+    test_sha1("tests/SHA1LongMsg.rsp");
+    test_sha1("tests/SHA1ShortMsg.rsp");
+    test_sha224("tests/SHA224LongMsg.rsp");
+    test_sha224("tests/SHA224ShortMsg.rsp");
     test_sha256("tests/SHA256LongMsg.rsp");
     test_sha256("tests/SHA256ShortMsg.rsp");
+    test_sha384("tests/SHA384LongMsg.rsp");
+    test_sha384("tests/SHA384ShortMsg.rsp");
     test_sha512("tests/SHA512LongMsg.rsp");
     test_sha512("tests/SHA512ShortMsg.rsp");
 }
+
+// NIST CAVP SHA-3 vectors, only a subset of the ShortMsg and VariableOut files
+#[test]
+fn nist_sha3() {
+    test_sha3("tests/SHA3_224ShortMsg.rsp", Sha3::sha3_224);
+    test_sha3("tests/SHA3_256ShortMsg.rsp", Sha3::sha3_256);
+    test_sha3("tests/SHA3_384ShortMsg.rsp", Sha3::sha3_384);
+    test_sha3("tests/SHA3_512ShortMsg.rsp", Sha3::sha3_512);
+
+    for (file, new) in [
+        (
+            "tests/SHAKE128VariableOut.rsp",
+            Sha3::shake128 as fn(usize) -> Sha3,
+        ),
+        ("tests/SHAKE256VariableOut.rsp", Sha3::shake256),
+    ] {
+        let rsp = read_rsp_file(file).expect("unable to read response file");
+        assert!(!rsp.cases.is_empty());
+
+        for x in rsp.cases {
+            let mut shake = new(x.output_length.unwrap() / 8);
+            shake.update(&x.message.unwrap());
+            shake.finalize();
+            assert_eq!(shake.to_string(), to_hex(&x.md));
+        }
+    }
+}
+
+// the SHA-3 and SHAKE files of tests/hashlib aren't NIST CAVP vectors but were computed with Python
+// hashlib: a cross-check of the response file handling and of longer messages with another
+// implementation, the NIST LongMsg and Monte files not being included
+#[test]
+fn all_sha3() {
+    for msg in &["ShortMsg", "LongMsg"] {
+        test_sha3(
+            &format!("tests/hashlib/SHA3_224{}.rsp", msg),
+            Sha3::sha3_224,
+        );
+        test_sha3(
+            &format!("tests/hashlib/SHA3_256{}.rsp", msg),
+            Sha3::sha3_256,
+        );
+        test_sha3(
+            &format!("tests/hashlib/SHA3_384{}.rsp", msg),
+            Sha3::sha3_384,
+        );
+        test_sha3(
+            &format!("tests/hashlib/SHA3_512{}.rsp", msg),
+            Sha3::sha3_512,
+        );
+        test_sha3(&format!("tests/hashlib/SHAKE128{}.rsp", msg), || {
+            Sha3::shake128(16)
+        });
+        test_sha3(&format!("tests/hashlib/SHAKE256{}.rsp", msg), || {
+            Sha3::shake256(32)
+        });
+    }
+}
+
+// the checkpoints computed from the seed must be the MD of each COUNT
+fn check_monte(response_file: &str, results: impl Fn(&[u8], usize) -> Vec<Vec<u8>>) {
+    let rsp = read_rsp_file(response_file).expect("unable to read response file");
+    assert!(rsp.is_monte());
+
+    let results = results(rsp.seed.as_ref().unwrap(), rsp.cases.len());
+    for (x, md) in rsp.cases.iter().zip(results) {
+        assert_eq!(to_hex(&md), to_hex(&x.md), "COUNT = {:?}", x.count);
+    }
+}
+
+#[test]
+fn monte_sha() {
+    check_monte("tests/SHA1Monte.rsp", |seed, n| {
+        sha2_monte(Sha1::new, seed, n)
+    });
+    check_monte("tests/SHA224Monte.rsp", |seed, n| {
+        sha2_monte(Sha256::sha224, seed, n)
+    });
+    check_monte("tests/SHA256Monte.rsp", |seed, n| {
+        sha2_monte(Sha256::new, seed, n)
+    });
+    check_monte("tests/SHA384Monte.rsp", |seed, n| {
+        sha2_monte(Sha512::sha384, seed, n)
+    });
+    check_monte("tests/SHA512Monte.rsp", |seed, n| {
+        sha2_monte(Sha512::new, seed, n)
+    });
+}
+
+#[test]
+fn monte_sha3() {
+    check_monte("tests/hashlib/SHA3_224Monte.rsp", |seed, n| {
+        sha3_monte(Sha3::sha3_224, seed, n)
+    });
+    check_monte("tests/hashlib/SHA3_256Monte.rsp", |seed, n| {
+        sha3_monte(Sha3::sha3_256, seed, n)
+    });
+    check_monte("tests/hashlib/SHA3_384Monte.rsp", |seed, n| {
+        sha3_monte(Sha3::sha3_384, seed, n)
+    });
+    check_monte("tests/hashlib/SHA3_512Monte.rsp", |seed, n| {
+        sha3_monte(Sha3::sha3_512, seed, n)
+    });
+}
+
+#[test]
+fn monte_shake() {
+    for (file, new) in [
+        (
+            "tests/hashlib/SHAKE128Monte.rsp",
+            Sha3::shake128 as fn(usize) -> Sha3,
+        ),
+        ("tests/hashlib/SHAKE256Monte.rsp", Sha3::shake256),
+    ] {
+        let rsp = read_rsp_file(file).expect("unable to read response file");
+        let bits = |name| rsp.parameter(name).unwrap().parse::<usize>().unwrap();
+        let (min, max) = (
+            bits("Minimum Output Length (bits)") / 8,
+            bits("Maximum Output Length (bits)") / 8,
+        );
+
+        check_monte(file, |msg, n| shake_monte(new, msg, min, max, n));
+        for x in &rsp.cases {
+            assert_eq!(x.output_length, Some(8 * x.md.len()));
+        }
+    }
+}
+
+// instantiate, then reseed once or before each generate with prediction resistance: the second
+// generate call gives the returned bits
+fn check_drbg<D: Drbg>(case: &DrbgTestCase, prediction_resistance: bool, mut drbg: D) {
+    let mut returned_bits = vec![0u8; case.returned_bits.len()];
+
+    if let Some(entropy_input) = &case.entropy_input_reseed {
+        let additional_input = case.additional_input_reseed.as_deref().unwrap_or_default();
+        drbg.reseed(entropy_input, additional_input).unwrap();
+    }
+
+    for i in 0..2 {
+        let additional_input = &case.additional_input[i];
+        if prediction_resistance {
+            let entropy_input = &case.entropy_input_pr[i];
+            drbg.generate_with_prediction_resistance(
+                &mut returned_bits,
+                entropy_input,
+                additional_input,
+            )
+        } else {
+            drbg.generate(&mut returned_bits, additional_input)
+        }
+        .unwrap();
+    }
+
+    assert_eq!(
+        to_hex(&returned_bits),
+        to_hex(&case.returned_bits),
+        "COUNT = {:?}",
+        case.count
+    );
+}
+
+// HMAC_DRBG.rsp and Hash_DRBG.rsp have NIST CAVS 14.3 vectors, but only one SHA-256 case of
+// Hash_DRBG: local/Hash_DRBG.rsp was generated with another implementation for the other hashes
+#[test]
+fn drbg_vectors() {
+    for file in [
+        "tests/HMAC_DRBG.rsp",
+        "tests/Hash_DRBG.rsp",
+        "tests/local/Hash_DRBG.rsp",
+    ] {
+        let hmac = file.contains("HMAC");
+        let sections = read_drbg_file(file).expect("unable to read response file");
+        assert!(!sections.is_empty());
+
+        for section in &sections {
+            let pr = section.prediction_resistance();
+            for x in &section.cases {
+                let (entropy, nonce, pers) =
+                    (&x.entropy_input, &x.nonce, &x.personalization_string);
+                match (section.algorithm(), hmac) {
+                    (Some("SHA-1"), true) => check_drbg(
+                        x,
+                        pr,
+                        HmacDrbg::new(Sha1::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-224"), true) => check_drbg(
+                        x,
+                        pr,
+                        HmacDrbg::new(Sha256::sha224(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-256"), true) => check_drbg(
+                        x,
+                        pr,
+                        HmacDrbg::new(Sha256::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-384"), true) => check_drbg(
+                        x,
+                        pr,
+                        HmacDrbg::new(Sha512::sha384(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-512"), true) => check_drbg(
+                        x,
+                        pr,
+                        HmacDrbg::new(Sha512::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-1"), false) => check_drbg(
+                        x,
+                        pr,
+                        HashDrbg::new(Sha1::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-224"), false) => check_drbg(
+                        x,
+                        pr,
+                        HashDrbg::new(Sha256::sha224(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-256"), false) => check_drbg(
+                        x,
+                        pr,
+                        HashDrbg::new(Sha256::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-384"), false) => check_drbg(
+                        x,
+                        pr,
+                        HashDrbg::new(Sha512::sha384(), entropy, nonce, pers).unwrap(),
+                    ),
+                    (Some("SHA-512"), false) => check_drbg(
+                        x,
+                        pr,
+                        HashDrbg::new(Sha512::new(), entropy, nonce, pers).unwrap(),
+                    ),
+                    // e.g. SHA-512/256: no section is silently skipped
+                    (algorithm, _) => panic!("{}: unsupported algorithm {:?}", file, algorithm),
+                }
+            }
+        }
+    }
+}